@@ -0,0 +1,282 @@
+use std::{
+    cmp::Ordering,
+    collections::{HashMap, HashSet},
+};
+
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::{Commands, Entity, Local, Query, ResMut, With, Without};
+use bevy_math::{Mat4, Vec2, Vec3, Vec4};
+use bevy_render::{
+    mesh::{Indices, Mesh},
+    pipeline::{PrimitiveTopology, RenderPipeline},
+    prelude::{Draw, RenderPipelines, Visible},
+};
+use bevy_sprite::ColorMaterial;
+use bevy_transform::components::{Children, GlobalTransform, Parent, Transform};
+
+use crate::{
+    render::{UI_BATCH_PIPELINE_HANDLE, UI_PIPELINE_HANDLE},
+    ANode, AuiRender, Fill, Gradient, ImageScale, PassThrough, TargetRoot,
+};
+
+// Opts a root into batched rendering. Its nodes are merged into one mesh for every run of nodes
+// that share a material, in the order the ui pass would have drawn them one by one, so overlapping
+// transparent nodes still blend the same. Nodes with a Fill, a Gradient, a contained or tiled image,
+// an atlas region, an opacity below 1, a clip rect or their own pipelines are drawn alone.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Batched;
+
+// A mesh drawing a run of batched nodes. It's placed where the run's first node is, so it gets
+// sorted among the other transparent draws just like that node would.
+#[derive(Clone, Debug, Default)]
+pub struct UiBatch {
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+}
+
+#[derive(Default)]
+pub struct BatchState {
+    // Nodes whose pipelines were taken away because a batch draws them instead
+    batched: HashSet<Entity>,
+    batches: HashMap<Entity, Vec<Entity>>,
+}
+
+struct DrawnNode {
+    entity: Entity,
+    world: Mat4,
+    origin: Vec3,
+    size: Vec2,
+    image_rect: Vec4,
+    material: Option<Handle<ColorMaterial>>,
+}
+
+type BatchNodeQuery<'a> = Query<
+    'a,
+    (
+        &'a Transform,
+        Option<&'a Children>,
+        Option<&'a AuiRender>,
+        Option<&'a Visible>,
+        Option<&'a Handle<ColorMaterial>>,
+        Option<&'a Fill>,
+        Option<&'a Gradient>,
+        Option<&'a ImageScale>,
+        Option<&'a mut RenderPipelines>,
+    ),
+    Without<UiBatch>,
+>;
+
+// Same corners and uvs as the quad mesh unbatched nodes are drawn with
+const QUAD_CORNERS: [(f32, f32); 4] = [(-0.5, -0.5), (-0.5, 0.5), (0.5, 0.5), (0.5, -0.5)];
+const QUAD_UVS: [[f32; 2]; 4] = [[0., 1.], [0., 0.], [1., 0.], [1., 1.]];
+const QUAD_INDICES: [u32; 6] = [0, 2, 1, 0, 3, 2];
+
+fn collect(
+    entity: Entity,
+    parent: Mat4,
+    nodes: &BatchNodeQuery,
+    state: &BatchState,
+    drawn: &mut Vec<DrawnNode>,
+) {
+    let transform = match nodes.get_component::<Transform>(entity) {
+        Ok(transform) => transform,
+        Err(_) => return,
+    };
+    let world = parent * transform.compute_matrix();
+    let shown = nodes
+        .get_component::<Visible>(entity)
+        .map_or(false, |visible| visible.is_visible);
+    if let (true, Ok(render)) = (shown, nodes.get_component::<AuiRender>(entity)) {
+        let default_pipeline =
+            nodes
+                .get_component::<RenderPipelines>(entity)
+                .map_or(false, |pipelines| {
+                    pipelines.pipelines.len() == 1
+                        && pipelines.pipelines[0].pipeline == UI_PIPELINE_HANDLE.typed()
+                });
+        // contained and tiled images need clipping and wrapping the shader does per node
+        let plain_image = nodes
+            .get_component::<ImageScale>(entity)
+            .map_or(true, |scale| {
+                *scale == ImageScale::Stretch || *scale == ImageScale::Cover
+            });
+        // samples are kept inside a node's atlas region by the shader, which batches can't do
+        let whole_texture = render.uv_rect == AuiRender::default().uv_rect;
+        let batchable = render.opacity >= 1.
+            && whole_texture
+            && render.clip == AuiRender::default().clip
+            && nodes.get_component::<Fill>(entity).is_err()
+            && nodes.get_component::<Gradient>(entity).is_err()
+            && plain_image
+            && (default_pipeline || state.batched.contains(&entity));
+        drawn.push(DrawnNode {
+            entity,
+            world,
+            origin: world.transform_point3(Vec3::zero()),
+            size: render.size,
+            image_rect: render.image_rect,
+            material: nodes
+                .get_component::<Handle<ColorMaterial>>(entity)
+                .ok()
+                .filter(|_| batchable)
+                .cloned(),
+        });
+    }
+    if let Ok(children) = nodes.get_component::<Children>(entity) {
+        for child in children.iter() {
+            collect(*child, world, nodes, state, drawn);
+        }
+    }
+}
+
+fn batch_mesh(positions: &[[f32; 3]], uvs: &[[f32; 2]]) -> Mesh {
+    let quads = positions.len() as u32 / 4;
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions.to_vec());
+    mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0., 0., 1.]; positions.len()]);
+    mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, uvs.to_vec());
+    mesh.set_indices(Some(Indices::U32(
+        (0..quads)
+            .flat_map(|quad| QUAD_INDICES.iter().map(move |index| quad * 4 + index))
+            .collect(),
+    )));
+    mesh
+}
+
+pub fn batch_system(
+    commands: &mut Commands,
+    mut state: Local<BatchState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    roots: Query<Entity, (With<Batched>, With<ANode>, Without<Parent>)>,
+    mut nodes: BatchNodeQuery,
+    mut batches: Query<(
+        &mut UiBatch,
+        &mut Transform,
+        &mut Handle<ColorMaterial>,
+        &Handle<Mesh>,
+    )>,
+) {
+    let mut batched = HashSet::new();
+    let mut root_batches = HashMap::new();
+    for root in roots.iter() {
+        let mut drawn = vec![];
+        collect(root, Mat4::identity(), &nodes, &state, &mut drawn);
+        // back to front, which is how the ui pass sorts transparent nodes
+        drawn.sort_by(|a, b| {
+            a.origin
+                .z
+                .partial_cmp(&b.origin.z)
+                .unwrap_or(Ordering::Equal)
+        });
+
+        // (material, origin, positions, uvs), nodes drawn alone in between end the current run
+        let mut runs: Vec<(Handle<ColorMaterial>, Vec3, Vec<[f32; 3]>, Vec<[f32; 2]>)> = vec![];
+        let mut extending = false;
+        for node in drawn {
+            let material = match node.material {
+                Some(material) => material,
+                None => {
+                    extending = false;
+                    continue;
+                }
+            };
+            batched.insert(node.entity);
+            if !extending || runs.last().map_or(true, |run| run.0 != material) {
+                runs.push((material, node.origin, vec![], vec![]));
+            }
+            extending = true;
+            let run = runs.last_mut().unwrap();
+            // the image rect is baked into the uvs, batches have no uniforms per node
+            let image_offset = Vec2::new(node.image_rect.x, node.image_rect.y);
+            let image_scale = Vec2::new(node.image_rect.z, node.image_rect.w);
+            for ((x, y), [u, v]) in QUAD_CORNERS.iter().zip(QUAD_UVS.iter()) {
+                let corner = Vec3::new(x * node.size.x, y * node.size.y, 0.);
+                let position = node.world.transform_point3(corner) - run.1;
+                run.2.push(position.into());
+                run.3
+                    .push((image_offset + Vec2::new(*u, *v) * image_scale).into());
+            }
+        }
+
+        let mut entities = state.batches.remove(&root).unwrap_or_default();
+        for excess in entities.drain(runs.len().min(entities.len())..) {
+            if let Ok((_, _, _, mesh)) = batches.get_mut(excess) {
+                meshes.remove(mesh);
+            }
+            commands.despawn(excess);
+        }
+        for (i, (material, origin, positions, uvs)) in runs.into_iter().enumerate() {
+            if let Some(entity) = entities.get(i) {
+                if let Ok((mut batch, mut transform, mut batch_material, mesh)) =
+                    batches.get_mut(*entity)
+                {
+                    if transform.translation != origin {
+                        transform.translation = origin;
+                    }
+                    if *batch_material != material {
+                        *batch_material = material;
+                    }
+                    if batch.positions != positions || batch.uvs != uvs {
+                        if let Some(mesh) = meshes.get_mut(mesh) {
+                            *mesh = batch_mesh(&positions, &uvs);
+                        }
+                        batch.positions = positions;
+                        batch.uvs = uvs;
+                    }
+                }
+                continue;
+            }
+            let entity = commands
+                .spawn((
+                    meshes.add(batch_mesh(&positions, &uvs)),
+                    Draw::default(),
+                    material,
+                    Visible {
+                        is_transparent: true,
+                        ..Default::default()
+                    },
+                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                        UI_BATCH_PIPELINE_HANDLE.typed(),
+                    )]),
+                    Transform::from_translation(origin),
+                    GlobalTransform::from_translation(origin),
+                    // the ui pass only draws entities with one of these
+                    AuiRender::default(),
+                    UiBatch { positions, uvs },
+                    TargetRoot(root),
+                    PassThrough,
+                ))
+                .current_entity()
+                .unwrap();
+            entities.push(entity);
+        }
+        root_batches.insert(root, entities);
+    }
+
+    // roots that stopped being batched, or were despawned
+    for (_, entities) in state.batches.drain() {
+        for entity in entities {
+            if let Ok((_, _, _, mesh)) = batches.get_mut(entity) {
+                meshes.remove(mesh);
+            }
+            commands.despawn(entity);
+        }
+    }
+    state.batches = root_batches;
+
+    for entity in batched.iter() {
+        if !state.batched.contains(entity) {
+            if let Ok(mut pipelines) = nodes.get_component_mut::<RenderPipelines>(*entity) {
+                pipelines.pipelines.clear();
+            }
+        }
+    }
+    for entity in state.batched.difference(&batched) {
+        if let Ok(mut pipelines) = nodes.get_component_mut::<RenderPipelines>(*entity) {
+            *pipelines = RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                UI_PIPELINE_HANDLE.typed(),
+            )]);
+        }
+    }
+    state.batched = batched;
+}
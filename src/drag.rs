@@ -0,0 +1,249 @@
+use bevy_app::Events;
+use bevy_asset::Handle;
+use bevy_ecs::{Commands, Entity, Local, Query, Res, ResMut, With};
+use bevy_input::{keyboard::KeyCode, mouse::MouseButton, Input};
+use bevy_math::Vec2;
+use bevy_render::{
+    mesh::Mesh,
+    pipeline::RenderPipeline,
+    prelude::{Draw, RenderPipelines, Visible},
+};
+use bevy_sprite::{ColorMaterial, QUAD_HANDLE};
+use bevy_transform::components::{GlobalTransform, Parent, Transform};
+
+use crate::{
+    interaction::contains, render::UI_PIPELINE_HANDLE, AuiRender, Interaction, PassThrough,
+    TargetRoot, UiCursor, UiTargets,
+};
+
+// Ghosts aren't part of any layout tree, so they're simply placed in front of everything else
+pub const GHOST_Z: f32 = 900.;
+// How far the cursor has to move while pressed before a drag starts
+pub const DRAG_THRESHOLD: f32 = 4.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DragPayload {
+    pub kind: u32,
+    pub data: u64,
+}
+
+// Needs an Interaction to be picked up
+#[derive(Clone, Copy, Debug)]
+pub struct Draggable {
+    pub payload: DragPayload,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct DropTarget {
+    pub accepts: fn(DragPayload) -> bool,
+}
+
+impl Default for DropTarget {
+    fn default() -> Self {
+        Self { accepts: |_| true }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum DragEvent {
+    Started {
+        entity: Entity,
+        payload: DragPayload,
+    },
+    // Sent whenever the drop target under the cursor changes
+    Over {
+        entity: Entity,
+        target: Option<Entity>,
+        accepted: bool,
+    },
+    Dropped {
+        entity: Entity,
+        target: Entity,
+        payload: DragPayload,
+    },
+    // Released outside of any accepting target, or escape was pressed
+    Cancelled {
+        entity: Entity,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DragGhost;
+
+struct Drag {
+    entity: Entity,
+    payload: DragPayload,
+    ghost: Entity,
+    grab_offset: Vec2,
+    over: Option<(Entity, bool)>,
+}
+
+#[derive(Default)]
+pub struct DragState {
+    pressed: Option<(Entity, Vec2)>,
+    dragging: Option<Drag>,
+    // Set when a drag is cancelled with the button still held, so the same press doesn't pick
+    // the node up again
+    cancelled_until_release: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn drag_system(
+    commands: &mut Commands,
+    mut state: Local<DragState>,
+    cursor: Res<UiCursor>,
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    ui_targets: Res<UiTargets>,
+    mut events: ResMut<Events<DragEvent>>,
+    draggables: Query<(
+        Entity,
+        &Draggable,
+        &Interaction,
+        &GlobalTransform,
+        &AuiRender,
+        &Handle<ColorMaterial>,
+    )>,
+    targets: Query<(
+        Entity,
+        &DropTarget,
+        &GlobalTransform,
+        &AuiRender,
+        Option<&Visible>,
+    )>,
+    parents: Query<&Parent>,
+    mut ghosts: Query<&mut Transform, With<DragGhost>>,
+) {
+    if state.cancelled_until_release {
+        if mouse.pressed(MouseButton::Left) {
+            return;
+        }
+        state.cancelled_until_release = false;
+    }
+
+    if state.dragging.is_none() {
+        if !mouse.pressed(MouseButton::Left) {
+            state.pressed = None;
+            return;
+        }
+        let position = match cursor.position {
+            Some(position) => position,
+            None => return,
+        };
+        let (entity, pressed_at) = match state.pressed {
+            Some(pressed) => pressed,
+            None => {
+                state.pressed = draggables
+                    .iter()
+                    .find(|(_, _, interaction, ..)| **interaction == Interaction::Pressed)
+                    .map(|(entity, ..)| (entity, position));
+                return;
+            }
+        };
+        if (position - pressed_at).length() < DRAG_THRESHOLD {
+            return;
+        }
+        let (_, draggable, _, transform, render, material) = match draggables.get(entity) {
+            Ok(draggable) => draggable,
+            Err(_) => {
+                state.pressed = None;
+                return;
+            }
+        };
+
+        let grab_offset = transform.translation.truncate() - pressed_at;
+        // the ghost is drawn into the same target as the node
+        let mut root = entity;
+        while let Ok(parent) = parents.get(root) {
+            root = parent.0;
+        }
+        let ghost = commands
+            .spawn((
+                QUAD_HANDLE.typed::<Mesh>(),
+                Draw::default(),
+                material.clone(),
+                Visible {
+                    is_transparent: true,
+                    ..Default::default()
+                },
+                RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                    UI_PIPELINE_HANDLE.typed(),
+                )]),
+                Transform::from_translation((position + grab_offset).extend(GHOST_Z)),
+                GlobalTransform::default(),
+                AuiRender {
+                    size: render.size,
+                    ..Default::default()
+                },
+                DragGhost,
+                TargetRoot(root),
+                // it's right under the cursor
+                PassThrough,
+            ))
+            .current_entity()
+            .unwrap();
+        events.send(DragEvent::Started {
+            entity,
+            payload: draggable.payload,
+        });
+        state.pressed = None;
+        state.dragging = Some(Drag {
+            entity,
+            payload: draggable.payload,
+            ghost,
+            grab_offset,
+            over: None,
+        });
+        return;
+    }
+
+    // without a cursor the ghost stays put, but the drag can still be released or cancelled
+    let drag = state.dragging.as_mut().unwrap();
+    if let Some(position) = cursor.position {
+        if let Ok(mut ghost) = ghosts.get_mut(drag.ghost) {
+            ghost.translation = (position + drag.grab_offset).extend(GHOST_Z);
+        }
+    }
+
+    let mut over = None;
+    let mut over_z = f32::MIN;
+    if let Some(position) = cursor.position {
+        for (entity, target, transform, render, visible) in targets.iter() {
+            if entity == drag.entity
+                || visible.map_or(false, |visible| !visible.is_visible)
+                || !ui_targets.same_target(entity, drag.entity)
+            {
+                continue;
+            }
+            if transform.translation.z > over_z && contains(transform, render, position) {
+                over_z = transform.translation.z;
+                over = Some((entity, (target.accepts)(drag.payload)));
+            }
+        }
+    }
+    if over != drag.over {
+        drag.over = over;
+        events.send(DragEvent::Over {
+            entity: drag.entity,
+            target: over.map(|(target, _)| target),
+            accepted: over.map_or(false, |(_, accepted)| accepted),
+        });
+    }
+
+    let cancelled = keys.just_pressed(KeyCode::Escape);
+    if cancelled || !mouse.pressed(MouseButton::Left) {
+        state.cancelled_until_release = cancelled && mouse.pressed(MouseButton::Left);
+        let drag = state.dragging.take().unwrap();
+        commands.despawn(drag.ghost);
+        match drag.over {
+            Some((target, true)) if !cancelled => events.send(DragEvent::Dropped {
+                entity: drag.entity,
+                target,
+                payload: drag.payload,
+            }),
+            _ => events.send(DragEvent::Cancelled {
+                entity: drag.entity,
+            }),
+        }
+    }
+}
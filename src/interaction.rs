@@ -0,0 +1,157 @@
+use bevy_app::{EventReader, Events};
+use bevy_ecs::{Entity, Local, Query, Res, ResMut, Without};
+use bevy_input::{mouse::MouseButton, Input};
+use bevy_math::Vec2;
+use bevy_render::prelude::Visible;
+use bevy_transform::components::{GlobalTransform, Parent};
+use bevy_window::{CursorMoved, WindowId, Windows};
+
+use crate::{AuiRender, UiTargets};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Interaction {
+    None,
+    Hovered,
+    Pressed,
+}
+
+impl Default for Interaction {
+    fn default() -> Self {
+        Interaction::None
+    }
+}
+
+// Every visible node blocks the cursor from reaching nodes below it, except for ones with this,
+// like decorations drawn over widgets
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PassThrough;
+
+// Disabled nodes still block the cursor from reaching nodes below them, but never get hovered
+// or pressed themselves
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Disabled;
+
+// Sent when a node is pressed and released without the cursor leaving it
+#[derive(Clone, Copy, Debug)]
+pub struct Clicked {
+    pub entity: Entity,
+}
+
+// Pressing a Focusable node gives it keyboard focus, pressing anything else takes it away
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Focusable;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UiFocus {
+    pub entity: Option<Entity>,
+}
+
+// Cursor position in UI space, where (0, 0) is the center of the window it's over. Windows with
+// a target of their own share that space with the primary one, so only nodes drawn into
+// `window` are reachable, see UiTargets::is_in_window.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UiCursor {
+    pub position: Option<Vec2>,
+    pub window: Option<WindowId>,
+}
+
+#[derive(Default)]
+pub struct InteractionState {
+    cursor_moved: EventReader<CursorMoved>,
+    pressed: Option<Entity>,
+}
+
+pub(crate) fn contains(transform: &GlobalTransform, render: &AuiRender, point: Vec2) -> bool {
+    let delta = (point - transform.translation.truncate()).abs();
+    let half = render.size / 2.;
+    delta.x <= half.x && delta.y <= half.y
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn interaction_system(
+    mut state: Local<InteractionState>,
+    cursor_moved: Res<Events<CursorMoved>>,
+    windows: Res<Windows>,
+    targets: Res<UiTargets>,
+    mouse: Res<Input<MouseButton>>,
+    mut cursor: ResMut<UiCursor>,
+    mut focus: ResMut<UiFocus>,
+    mut clicks: ResMut<Events<Clicked>>,
+    nodes: Query<(Entity, &GlobalTransform, &AuiRender, Option<&Visible>), Without<PassThrough>>,
+    parents: Query<&Parent>,
+    mut interactions: Query<(
+        Entity,
+        &mut Interaction,
+        Option<&Disabled>,
+        Option<&Focusable>,
+    )>,
+) {
+    let primary = windows.get_primary().map(|window| window.id());
+    for event in state.cursor_moved.iter(&cursor_moved) {
+        if !targets.has_window(event.id) && Some(event.id) != primary {
+            continue;
+        }
+        if let Some(window) = windows.get(event.id) {
+            let half_window = Vec2::new(window.width(), window.height()) / 2.;
+            cursor.position = Some(event.position - half_window);
+            cursor.window = Some(event.id);
+        }
+    }
+
+    // the topmost node under the cursor wins, nodes are closer the higher their z
+    let mut topmost = None;
+    if let (Some(position), Some(window)) = (cursor.position, cursor.window) {
+        let mut topmost_z = f32::MIN;
+        for (entity, transform, render, visible) in nodes.iter() {
+            if visible.map_or(false, |visible| !visible.is_visible)
+                || !targets.is_in_window(entity, window)
+            {
+                continue;
+            }
+            if transform.translation.z > topmost_z && contains(transform, render, position) {
+                topmost_z = transform.translation.z;
+                topmost = Some(entity);
+            }
+        }
+    }
+    // and gives the interaction to itself or its closest ancestor with one, so the contents of a
+    // button don't keep it from being pressed
+    let mut hovered = None;
+    let mut hovered_focusable = false;
+    let mut node = topmost;
+    while let Some(entity) = node {
+        if let Ok((_, _, disabled, focusable)) = interactions.get_mut(entity) {
+            if disabled.is_none() {
+                hovered = Some(entity);
+                hovered_focusable = focusable.is_some();
+            }
+            break;
+        }
+        node = parents.get(entity).ok().map(|parent| parent.0);
+    }
+
+    if mouse.just_pressed(MouseButton::Left) {
+        state.pressed = hovered;
+        focus.entity = if hovered_focusable { hovered } else { None };
+    }
+    if mouse.just_released(MouseButton::Left) {
+        if let Some(pressed) = state.pressed.take() {
+            if hovered == Some(pressed) {
+                clicks.send(Clicked { entity: pressed });
+            }
+        }
+    }
+
+    for (entity, mut interaction, ..) in interactions.iter_mut() {
+        let new = if state.pressed == Some(entity) {
+            Interaction::Pressed
+        } else if hovered == Some(entity) && state.pressed.is_none() {
+            Interaction::Hovered
+        } else {
+            Interaction::None
+        };
+        if *interaction != new {
+            *interaction = new;
+        }
+    }
+}
@@ -2,83 +2,164 @@ use std::collections::BTreeMap;
 
 use bevy_ecs::{Entity, Flags, Query};
 use bevy_math::Vec2;
-use bevy_transform::components::{Children, Transform};
+use bevy_transform::components::{Children, Parent, Transform};
 
-use crate::{ANode, ANodeLayoutCache, AuiRender, AxisConstraint, Constraint, Direction};
+use crate::{
+    ANode, ANodeLayoutCache, AuiRender, AxisConstraint, ChildConstraint, ChildLength, Constraint,
+    Direction, DisplayMode, IntrinsicSize, LayoutDirection,
+};
 
-pub const UI_Z_STEP: f32 = -0.001;
+pub(crate) type NodeQuery<'a> = Query<
+    'a,
+    (
+        &'a ANode,
+        Flags<ANode>,
+        Option<&'a Children>,
+        Option<Flags<Children>>,
+        Option<&'a IntrinsicSize>,
+        Option<Flags<IntrinsicSize>>,
+        Option<&'a DisplayMode>,
+        Option<Flags<DisplayMode>>,
+    ),
+>;
 
+// `unclipped` is the size of the whole target and its center, relative to the same parent as
+// `origin`, for nodes that ignore the safe area
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn solve(
     solve_entity: Entity,
     space: Vec2,
-    active_z: f32,
+    origin: Vec2,
+    unclipped: (Vec2, Vec2),
+    rtl: bool,
     respect_flags: bool,
-    nodes: &Query<(&ANode, Flags<ANode>, Option<&Children>, Option<Flags<Children>>)>,
+    nodes: &NodeQuery,
     mutables: &mut Query<(&mut Transform, &mut AuiRender, &mut ANodeLayoutCache)>,
 ) {
     let (mut target_transform, mut render_data, cache) = mutables.get_mut(solve_entity).unwrap();
     let target_size = &mut render_data.size;
-    let (solve_target, node_flags, children, children_flags) = nodes.get(solve_entity).unwrap();
+    let (solve_target, node_flags, children, children_flags, _, _, _, display_flags) =
+        nodes.get(solve_entity).unwrap();
+    let rtl = solve_target
+        .layout_direction
+        .map_or(rtl, |direction| direction == LayoutDirection::RightToLeft);
+    let (space, origin) = if solve_target.ignore_safe_area {
+        unclipped
+    } else {
+        (space, origin)
+    };
 
-    if respect_flags && !node_flags.changed() {
+    if respect_flags && !node_flags.changed() && !display_flags.map_or(false, |f| f.changed()) {
         if let Some(children) = children {
-            let solve_self =
-                |transforms| solve(solve_entity, space, active_z, false, nodes, transforms);
-            let ts = target_size.clone();
+            let solve_self = |transforms| {
+                solve(
+                    solve_entity,
+                    space,
+                    origin,
+                    unclipped,
+                    rtl,
+                    false,
+                    nodes,
+                    transforms,
+                )
+            };
+            let ts = *target_size;
+            let unclipped = (
+                unclipped.0,
+                unclipped.1 - target_transform.translation.truncate(),
+            );
             if solve_target.children_spread.is_some() {
                 if children_flags.unwrap().changed() {
                     solve_self(mutables);
                     return;
                 }
                 for child in children.iter() {
-                    let child = nodes.get(*child).unwrap();
-                    if child.1.changed() {
-                        solve_self(mutables);
-                        return;
+                    if let Ok((_, flags, _, _, _, intrinsic_flags, _, display_flags)) =
+                        nodes.get(*child)
+                    {
+                        if flags.changed()
+                            || intrinsic_flags.map_or(false, |f| f.changed())
+                            || display_flags.map_or(false, |f| f.changed())
+                        {
+                            solve_self(mutables);
+                            return;
+                        }
                     }
                 }
                 let cache = cache.sizes.as_ref().unwrap().clone();
-                for (child, size) in children.iter().zip(cache.iter()) {
-                    solve(*child, *size, active_z + UI_Z_STEP, true, nodes, mutables)
+                for &(child, size, origin) in cache.iter() {
+                    solve(child, size, origin, unclipped, rtl, true, nodes, mutables)
+                }
+                for child in children.iter().filter(|c| is_absolute(nodes, **c)) {
+                    solve(
+                        *child,
+                        ts,
+                        Vec2::zero(),
+                        unclipped,
+                        rtl,
+                        true,
+                        nodes,
+                        mutables,
+                    )
                 }
             } else {
-                for child in children.iter() {
-                    solve(*child, ts, active_z + UI_Z_STEP, true, nodes, mutables)
+                for child in children
+                    .iter()
+                    .filter(|c| laid_out_node(nodes, **c).is_some())
+                {
+                    solve(
+                        *child,
+                        ts,
+                        Vec2::zero(),
+                        unclipped,
+                        rtl,
+                        true,
+                        nodes,
+                        mutables,
+                    )
                 }
             }
         }
         return;
     }
 
-    let mut offset = match &solve_target.constraint {
+    let (anchors, constraint) = if rtl {
+        (
+            solve_target.anchors.mirrored_x(),
+            solve_target.constraint.mirrored(),
+        )
+    } else {
+        (
+            solve_target.anchors.clone(),
+            solve_target.constraint.clone(),
+        )
+    };
+
+    let mut offset = match &constraint {
         Constraint::Independent { x, y } => {
-            let x = x.solve(solve_target.anchors.x(), space.x);
-            let y = y.solve(solve_target.anchors.y(), space.y);
+            let x = x.solve(anchors.x(), space.x);
+            let y = y.solve(anchors.y(), space.y);
 
             *target_size = Vec2::new(x.size, y.size);
             Vec2::new(x.offset, y.offset)
         }
         Constraint::SetXWithY { y, aspect } => {
-            let y = y.solve(solve_target.anchors.y(), space.y);
-            let x =
-                AxisConstraint::Centered(y.size * aspect).solve(solve_target.anchors.x(), space.x);
+            let y = y.solve(anchors.y(), space.y);
+            let x = AxisConstraint::Centered(y.size * aspect).solve(anchors.x(), space.x);
 
             *target_size = Vec2::new(x.size, y.size);
             Vec2::new(x.offset, y.offset)
         }
         Constraint::SetYWithX { x, aspect } => {
-            let x = x.solve(solve_target.anchors.x(), space.x);
-            let y =
-                AxisConstraint::Centered(x.size / aspect).solve(solve_target.anchors.y(), space.y);
+            let x = x.solve(anchors.x(), space.x);
+            let y = AxisConstraint::Centered(x.size / aspect).solve(anchors.y(), space.y);
 
             *target_size = Vec2::new(x.size, y.size);
             Vec2::new(x.offset, y.offset)
         }
         Constraint::MaxAspect(aspect) => {
-            let x_from_y =
-                (solve_target.anchors.y().1 - solve_target.anchors.y().0) * space.y * aspect;
-            let y_from_x =
-                (solve_target.anchors.x().1 - solve_target.anchors.x().0) * space.x / aspect;
+            let x_from_y = (anchors.y().1 - anchors.y().0) * space.y * aspect;
+            let y_from_x = (anchors.x().1 - anchors.x().0) * space.x / aspect;
 
             *target_size = if x_from_y >= space.x {
                 Vec2::new(space.x, y_from_x)
@@ -89,42 +170,65 @@ pub(crate) fn solve(
         }
     };
 
-    if solve_target.child_constraint.is_some() {
-        offset += target_transform.translation.truncate();
-    };
+    offset += origin;
 
-    target_transform.translation = offset.extend(active_z);
-    let active_z = active_z + UI_Z_STEP;
+    // z is left to draw_order_system, layout only places nodes in the plane
+    let z = target_transform.translation.z;
+    target_transform.translation = offset.extend(z);
+    let unclipped = (unclipped.0, unclipped.1 - offset);
 
     if let Some(children) = children {
-        let ts = target_size.clone();
+        let ts = *target_size;
         if let Some(spread_constraint) = &solve_target.children_spread {
-            let child_nodes = children.iter().map(|c| {
-                (
-                    nodes
-                        .get_component::<ANode>(*c)
-                        .unwrap()
+            let direction = if rtl {
+                spread_constraint.direction.mirrored()
+            } else {
+                spread_constraint.direction.clone()
+            };
+            let along = |v: Vec2| match direction {
+                Direction::Left | Direction::Right => v.x,
+                Direction::Up | Direction::Down => v.y,
+            };
+
+            // children without an ANode or that are collapsed are skipped entirely
+            let child_nodes = children
+                .iter()
+                .filter_map(|c| {
+                    let node = laid_out_node(nodes, *c)?;
+                    if node.absolute {
+                        return None;
+                    }
+                    let intrinsic = nodes.get_component::<IntrinsicSize>(*c).ok();
+                    let constraint = node
                         .child_constraint
                         .as_ref()
-                        .unwrap(),
-                    c,
-                )
-            });
+                        .unwrap_or(&ChildConstraint::DEFAULT);
+                    Some((constraint, intrinsic, c))
+                })
+                .collect::<Vec<_>>();
 
-            let mut free_length = match spread_constraint.direction {
-                Direction::Left | Direction::Right => ts.x,
-                Direction::Up | Direction::Down => ts.y,
-            } - (children.iter().count() - 1) as f32
-                * spread_constraint.margin;
+            let mut free_length =
+                along(ts) - child_nodes.len().saturating_sub(1) as f32 * spread_constraint.margin;
 
             let mut undef = vec![];
             let mut undef_weight_sum = 0.;
 
             let mut locked = BTreeMap::<usize, (&Entity, f32)>::new();
 
-            for (i, c) in child_nodes.enumerate() {
-                undef_weight_sum += c.0.weight;
-                undef.push((i, c));
+            for (i, (n, intrinsic, e)) in child_nodes.into_iter().enumerate() {
+                let fixed = match n.length {
+                    ChildLength::Weighted => None,
+                    ChildLength::Fixed(len) => Some(len),
+                    ChildLength::Intrinsic => Some(intrinsic.map_or(0., |s| along(s.0))),
+                };
+                if let Some(len) = fixed {
+                    let len = len.clamp(n.min_size, n.max_size);
+                    free_length -= len;
+                    locked.insert(i, (e, len));
+                } else {
+                    undef_weight_sum += n.weight;
+                    undef.push((i, (n, e)));
+                }
             }
 
             loop {
@@ -157,7 +261,7 @@ pub(crate) fn solve(
             }
 
             let (calc_pos, calc_size): (fn(f32, f32, Vec2) -> Vec2, fn(f32, Vec2) -> Vec2) =
-                match spread_constraint.direction {
+                match direction {
                     Direction::Up => (
                         |size, offset, ts| Vec2::new(0., offset + size / 2. - ts.y / 2.),
                         |size, ts| Vec2::new(ts.x, size),
@@ -179,23 +283,91 @@ pub(crate) fn solve(
             let mut offset = 0.;
             let mut cache = vec![];
             for &(&entity, size) in locked.values() {
-                let (mut transform, _, _) = mutables.get_mut(entity).unwrap();
-                transform.translation = calc_pos(size, offset, ts).extend(0.);
+                let origin = calc_pos(size, offset, ts);
                 offset += size + spread_constraint.margin;
                 let size = calc_size(size, ts);
-                cache.push(size);
-                solve(entity, size, active_z, respect_flags, nodes, mutables);
+                cache.push((entity, size, origin));
+                solve(
+                    entity,
+                    size,
+                    origin,
+                    unclipped,
+                    rtl,
+                    respect_flags,
+                    nodes,
+                    mutables,
+                );
             }
             let (_, _, mut target_cache) = mutables.get_mut(solve_entity).unwrap();
             target_cache.sizes = Some(cache);
+
+            for child in children.iter().filter(|c| is_absolute(nodes, **c)) {
+                solve(
+                    *child,
+                    ts,
+                    Vec2::zero(),
+                    unclipped,
+                    rtl,
+                    false,
+                    nodes,
+                    mutables,
+                );
+            }
         } else {
-            for child in children.iter() {
-                solve(*child, ts, active_z, false, nodes, mutables);
+            for child in children
+                .iter()
+                .filter(|c| laid_out_node(nodes, **c).is_some())
+            {
+                solve(
+                    *child,
+                    ts,
+                    Vec2::zero(),
+                    unclipped,
+                    rtl,
+                    false,
+                    nodes,
+                    mutables,
+                );
             }
         }
     }
 }
 
+// The direction a node is laid out in, from the closest ancestor that overrides it
+pub(crate) fn resolve_direction(
+    entity: Entity,
+    default: LayoutDirection,
+    nodes: &Query<(Option<&ANode>, Option<&Parent>)>,
+) -> LayoutDirection {
+    let mut current = Some(entity);
+    while let Some(entity) = current {
+        let (node, parent) = match nodes.get(entity) {
+            Ok(node) => node,
+            Err(_) => break,
+        };
+        if let Some(direction) = node.and_then(|node| node.layout_direction) {
+            return direction;
+        }
+        current = parent.map(|parent| parent.0);
+    }
+    default
+}
+
+fn laid_out_node<'a>(nodes: &'a NodeQuery, entity: Entity) -> Option<&'a ANode> {
+    let collapsed = nodes
+        .get_component::<DisplayMode>(entity)
+        .map_or(false, |mode| *mode == DisplayMode::Collapsed);
+    if collapsed {
+        None
+    } else {
+        nodes.get_component::<ANode>(entity).ok()
+    }
+}
+
+fn is_absolute(nodes: &NodeQuery, entity: Entity) -> bool {
+    laid_out_node(nodes, entity).map_or(false, |node| node.absolute)
+}
+
 impl AxisConstraint {
     fn solve(self, anchors: (f32, f32), true_space: f32) -> AxisConstraintSolve {
         let space = (anchors.1 - anchors.0) * true_space;
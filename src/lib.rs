@@ -1,25 +1,44 @@
+use std::collections::{HashMap, VecDeque};
+
 use bevy_app::{stage, Plugin};
 use bevy_asset::Handle;
-use bevy_ecs::{Bundle, Flags, Local, SystemStage};
-use bevy_ecs::{Entity, IntoSystem, Query, Res, With, Without};
-use bevy_math::{Vec2, Vec3};
+use bevy_ecs::{Bundle, Changed, Local, SystemStage};
+use bevy_ecs::{Entity, IntoSystem, Or, Query, Res, ResMut, With, Without};
+use bevy_math::{Vec2, Vec3, Vec4};
 use bevy_render::{
-    camera::{Camera, OrthographicProjection, VisibleEntities, WindowOrigin},
+    camera::{
+        Camera, CameraProjection, DepthCalculation, OrthographicProjection, VisibleEntities,
+        WindowOrigin,
+    },
     mesh::Mesh,
-    pipeline::RenderPipeline,
+    pipeline::{PipelineDescriptor, RenderPipeline},
     prelude::{Draw, RenderPipelines, Visible},
     render_graph::RenderGraph,
 };
 use bevy_sprite::{ColorMaterial, QUAD_HANDLE};
 use bevy_transform::components::{Children, GlobalTransform, Parent, Transform};
-use bevy_window::Windows;
+use bevy_window::{WindowId, Windows};
 
+pub mod batch;
+pub mod drag;
+pub mod interaction;
 mod layout;
 mod render;
+pub mod shadow;
 pub mod types;
+pub mod widgets;
 
-use render::{UiRenderGraphBuilder, UI_PIPELINE_HANDLE};
+pub use batch::*;
+pub use drag::*;
+pub use interaction::*;
+use render::UI_PIPELINE_HANDLE;
+pub use render::{build_ui_material_pipeline, UiRenderGraphBuilder};
+pub use shadow::*;
 pub use types::*;
+pub use widgets::{
+    button::*, checkbox::*, dropdown::*, image::*, slider::*, split::*, tabs::*, text_input::*,
+    tooltip::*, virtual_list::*, ValueChanged,
+};
 
 #[derive(Bundle, Clone, Debug)]
 pub struct AUINode {
@@ -31,6 +50,7 @@ pub struct AUINode {
     pub transform: Transform,
     pub global_transform: GlobalTransform,
     pub node: ANode,
+    pub display: DisplayMode,
     pub layout_cache: ANodeLayoutCache,
     pub render_data: AuiRender,
 }
@@ -47,6 +67,7 @@ impl Default for AUINode {
                 ..Default::default()
             },
             node: Default::default(),
+            display: Default::default(),
             material: Default::default(),
             draw: Default::default(),
             transform: Default::default(),
@@ -57,6 +78,18 @@ impl Default for AUINode {
     }
 }
 
+impl AUINode {
+    // A node drawn with its own pipeline, built with build_ui_material_pipeline. Its material is
+    // added to the entity next to this bundle, once the type is registered with
+    // UiRenderGraphBuilder::add_ui_material.
+    pub fn with_pipeline(pipeline: Handle<PipelineDescriptor>) -> Self {
+        Self {
+            render_pipelines: RenderPipelines::from_pipelines(vec![RenderPipeline::new(pipeline)]),
+            ..Default::default()
+        }
+    }
+}
+
 // Camera taken from bevy_ui
 
 #[derive(Bundle, Debug)]
@@ -76,6 +109,7 @@ impl Default for AUiCameraBundle {
         AUiCameraBundle {
             camera: Camera {
                 name: Some(render::camera::CAMERA_UI.to_string()),
+                depth_calculation: DepthCalculation::ZDifference,
                 ..Default::default()
             },
             orthographic_projection: OrthographicProjection {
@@ -90,27 +124,102 @@ impl Default for AUiCameraBundle {
     }
 }
 
+impl AUiCameraBundle {
+    // A camera for a texture target, its projection is fit to the texture by target_camera_system
+    pub fn for_texture(camera: &str) -> Self {
+        let default = Self::default();
+        AUiCameraBundle {
+            camera: Camera {
+                name: Some(camera.to_string()),
+                // a window that doesn't exist, so resizes never touch the projection
+                window: WindowId::new(),
+                ..default.camera
+            },
+            ..default
+        }
+    }
+
+    // A camera for a window target. Its projection follows the window like the primary ui
+    // camera's does.
+    pub fn for_window(camera: &str, window: WindowId) -> Self {
+        let default = Self::default();
+        AUiCameraBundle {
+            camera: Camera {
+                name: Some(camera.to_string()),
+                window,
+                ..default.camera
+            },
+            ..default
+        }
+    }
+}
+
 pub fn layout_system(
-    roots: Query<Entity, (With<ANode>, Without<Parent>)>,
-    nodes: Query<(&ANode, Flags<ANode>, Option<&Children>, Option<Flags<Children>>)>,
+    roots: Query<
+        (
+            Entity,
+            Option<&SafeArea>,
+            Option<&UiTexture>,
+            Option<&UiWindow>,
+        ),
+        (With<ANode>, Without<Parent>),
+    >,
+    nodes: layout::NodeQuery,
     mut transforms: Query<(&mut Transform, &mut AuiRender, &mut ANodeLayoutCache)>,
     windows: Res<Windows>,
-    mut local: Local<Vec<Vec2>>,
+    direction: Res<LayoutDirection>,
+    safe_area: Res<SafeArea>,
+    targets: Res<UiTargets>,
+    mut local: Local<HashMap<Entity, (Vec2, Vec2, Vec2)>>,
+    mut last_direction: Local<LayoutDirection>,
 ) {
-    for (i, (root, window)) in roots.iter().zip(windows.iter()).enumerate() {
-        let window_size = Vec2::new(window.width(), window.height());
-        let root_change = if let Some(old_size) = local.get_mut(i) {
-            if *old_size == window_size {
-                false
-            } else {
-                *old_size = window_size;
-                true
+    let direction_change = *last_direction != *direction;
+    *last_direction = *direction;
+    let rtl = *direction == LayoutDirection::RightToLeft;
+
+    // roots without a target of their own are paired up with the windows that have none either
+    let mut free_windows = windows
+        .iter()
+        .filter(|window| !targets.has_window(window.id()));
+    for (root, root_safe_area, texture, window) in roots.iter() {
+        // the global safe area is about the screen, so texture roots only use their own
+        let (target_size, safe_area) = match (texture, window) {
+            (Some(texture), _) => match targets.texture_size(&texture.camera) {
+                Some(size) => (size, root_safe_area.copied().unwrap_or_default()),
+                None => continue,
+            },
+            (None, Some(target)) => {
+                match (targets.index(&target.camera), windows.get(target.window)) {
+                    (Some(_), Some(window)) => (
+                        Vec2::new(window.width(), window.height()),
+                        *root_safe_area.unwrap_or(&safe_area),
+                    ),
+                    _ => continue,
+                }
             }
-        } else {
-            local.push(window_size);
-            true
+            (None, None) => match free_windows.next() {
+                Some(window) => (
+                    Vec2::new(window.width(), window.height()),
+                    *root_safe_area.unwrap_or(&safe_area),
+                ),
+                None => continue,
+            },
         };
-        layout::solve(root, window_size, 50., !root_change, &nodes, &mut transforms);
+        let (space, origin) = safe_area.shrink(target_size);
+        // nodes that ignore the safe area anywhere in the tree are laid out against all of it
+        let bounds = (space, origin, target_size);
+        let space_change = local.insert(root, bounds) != Some(bounds);
+        let root_change = space_change || direction_change;
+        layout::solve(
+            root,
+            space,
+            origin,
+            (target_size, Vec2::zero()),
+            rtl,
+            !root_change,
+            &nodes,
+            &mut transforms,
+        );
     }
     // println!("-------");
     // for (t, s) in transforms.iter_mut() {
@@ -118,13 +227,362 @@ pub fn layout_system(
     // }
 }
 
+// The ui cameras sort transparent draws by z, so every node gets a z this far apart in painter's
+// order: parents before their children, children in order and overlays after everything else
+pub const DRAW_ORDER_STEP: f32 = 0.001;
+
+pub fn draw_order_system(
+    roots: Query<Entity, (With<ANode>, Without<Parent>)>,
+    nodes: Query<(Option<&ANode>, Option<&Children>)>,
+    mut transforms: Query<&mut Transform>,
+) {
+    // overlays are queued with the global z of their parent
+    fn walk(
+        entity: Entity,
+        parent_z: f32,
+        overlay_start: bool,
+        nodes: &Query<(Option<&ANode>, Option<&Children>)>,
+        transforms: &mut Query<&mut Transform>,
+        order: &mut u32,
+        overlays: &mut VecDeque<(Entity, f32)>,
+    ) {
+        let (node, children) = match nodes.get(entity) {
+            Ok(node) => node,
+            Err(_) => return,
+        };
+        // entities that aren't nodes keep their own offset from their parent
+        let z = match node {
+            Some(node) if node.overlay && !overlay_start => {
+                overlays.push_back((entity, parent_z));
+                return;
+            }
+            Some(_) => {
+                *order += 1;
+                let z = *order as f32 * DRAW_ORDER_STEP;
+                if let Ok(mut transform) = transforms.get_mut(entity) {
+                    if transform.translation.z != z - parent_z {
+                        transform.translation.z = z - parent_z;
+                    }
+                }
+                z
+            }
+            None => {
+                parent_z
+                    + transforms
+                        .get_component::<Transform>(entity)
+                        .map_or(0., |transform| transform.translation.z)
+            }
+        };
+        for child in children.iter().flat_map(|children| children.iter()) {
+            walk(*child, z, false, nodes, transforms, order, overlays);
+        }
+    }
+
+    let mut order = 0;
+    let mut overlays = VecDeque::new();
+    for root in roots.iter() {
+        walk(
+            root,
+            0.,
+            false,
+            &nodes,
+            &mut transforms,
+            &mut order,
+            &mut overlays,
+        );
+        while let Some((overlay, parent_z)) = overlays.pop_front() {
+            walk(
+                overlay,
+                parent_z,
+                true,
+                &nodes,
+                &mut transforms,
+                &mut order,
+                &mut overlays,
+            );
+        }
+    }
+}
+
+// Fits the projection of texture target cameras to their texture, windows do this themselves
+pub fn target_camera_system(
+    targets: Res<UiTargets>,
+    mut cameras: Query<(&mut Camera, &mut OrthographicProjection)>,
+) {
+    for (mut camera, mut projection) in cameras.iter_mut() {
+        let size = match camera
+            .name
+            .as_ref()
+            .and_then(|name| targets.texture_size(name))
+        {
+            Some(size) => size,
+            None => continue,
+        };
+        if projection.right - projection.left != size.x
+            || projection.top - projection.bottom != size.y
+        {
+            projection.left = -size.x / 2.;
+            projection.right = size.x / 2.;
+            projection.bottom = -size.y / 2.;
+            projection.top = size.y / 2.;
+            camera.projection_matrix = projection.get_projection_matrix();
+        }
+    }
+}
+
+// Every ui camera sees every node, so each one is left with the entities of its own target.
+// Runs once visible entities have been collected for the frame.
+pub fn target_visibility_system(
+    mut targets: ResMut<UiTargets>,
+    roots: Query<(Entity, Option<&UiTexture>, Option<&UiWindow>), Without<Parent>>,
+    children: Query<&Children>,
+    drawn_for_roots: Query<(Entity, &TargetRoot)>,
+    mut cameras: Query<(&Camera, &mut VisibleEntities)>,
+) {
+    fn own(
+        entity: Entity,
+        index: usize,
+        children: &Query<&Children>,
+        owners: &mut HashMap<Entity, usize>,
+    ) {
+        owners.insert(entity, index);
+        if let Ok(entity_children) = children.get(entity) {
+            for child in entity_children.iter() {
+                own(*child, index, children, owners);
+            }
+        }
+    }
+
+    let mut owners = HashMap::new();
+    for (root, texture, window) in roots.iter() {
+        let camera = match (texture, window) {
+            (Some(texture), _) => &texture.camera,
+            (None, Some(window)) => &window.camera,
+            (None, None) => continue,
+        };
+        if let Some(index) = targets.index(camera) {
+            own(root, index, &children, &mut owners);
+        }
+    }
+    for (entity, root) in drawn_for_roots.iter() {
+        if let Some(index) = owners.get(&root.0).copied() {
+            own(entity, index, &children, &mut owners);
+        }
+    }
+
+    for (camera, mut visible_entities) in cameras.iter_mut() {
+        let index = match camera.name.as_deref() {
+            Some(render::camera::CAMERA_UI) => None,
+            Some(name) => match targets.index(name) {
+                Some(index) => Some(index),
+                None => continue,
+            },
+            None => continue,
+        };
+        visible_entities
+            .value
+            .retain(|visible| owners.get(&visible.entity).copied() == index);
+    }
+    targets.owners = owners;
+}
+
+pub fn fill_system(
+    fills: Query<(Entity, &Fill), Changed<Fill>>,
+    mut renders: Query<&mut AuiRender>,
+) {
+    for (entity, fill) in fills.iter() {
+        if let Ok(mut render_data) = renders.get_mut(entity) {
+            render_data.fill = fill.as_vec4();
+        }
+    }
+    // nodes that lost their Fill are drawn whole again
+    for entity in fills.removed::<Fill>() {
+        if let Ok(mut render_data) = renders.get_mut(*entity) {
+            render_data.fill = Vec4::zero();
+        }
+    }
+}
+
+pub fn gradient_system(
+    gradients: Query<(Entity, &Gradient), Changed<Gradient>>,
+    mut renders: Query<&mut AuiRender>,
+) {
+    for (entity, gradient) in gradients.iter() {
+        if let Ok(mut render_data) = renders.get_mut(entity) {
+            let (shape, params, stops, colors) = gradient.as_uniforms();
+            render_data.gradient = shape;
+            render_data.gradient_shape = params;
+            render_data.gradient_stops = stops;
+            render_data.gradient_colors = colors;
+        }
+    }
+    for entity in gradients.removed::<Gradient>() {
+        if let Ok(mut render_data) = renders.get_mut(*entity) {
+            let default = AuiRender::default();
+            render_data.gradient = default.gradient;
+            render_data.gradient_shape = default.gradient_shape;
+            render_data.gradient_stops = default.gradient_stops;
+            render_data.gradient_colors = default.gradient_colors;
+        }
+    }
+}
+
+// Visible is only written when the shown state a node inherits changes, so anything else setting
+// is_visible on a shown node keeps working
+pub fn display_system(
+    mut last_shown: Local<HashMap<Entity, bool>>,
+    roots: Query<Entity, (With<ANode>, Without<Parent>)>,
+    nodes: Query<(Option<&DisplayMode>, Option<&Children>)>,
+    mut visibles: Query<&mut Visible>,
+) {
+    fn propagate(
+        entity: Entity,
+        parent_shown: bool,
+        nodes: &Query<(Option<&DisplayMode>, Option<&Children>)>,
+        visibles: &mut Query<&mut Visible>,
+        last_shown: &HashMap<Entity, bool>,
+        shown_now: &mut HashMap<Entity, bool>,
+    ) {
+        let (mode, children) = match nodes.get(entity) {
+            Ok(node) => node,
+            Err(_) => return,
+        };
+        let shown = parent_shown && mode.map_or(true, |mode| *mode == DisplayMode::Shown);
+        // new entities start out visible, so only hiding them needs a write
+        if last_shown.get(&entity).copied().unwrap_or(true) != shown {
+            if let Ok(mut visible) = visibles.get_mut(entity) {
+                visible.is_visible = shown;
+            }
+        }
+        shown_now.insert(entity, shown);
+        if let Some(children) = children {
+            for child in children.iter() {
+                propagate(*child, shown, nodes, visibles, last_shown, shown_now);
+            }
+        }
+    }
+
+    let mut shown_now = HashMap::with_capacity(last_shown.len());
+    for root in roots.iter() {
+        propagate(
+            root,
+            true,
+            &nodes,
+            &mut visibles,
+            &last_shown,
+            &mut shown_now,
+        );
+    }
+    *last_shown = shown_now;
+}
+
+pub fn opacity_system(
+    roots: Query<Entity, (With<ANode>, Without<Parent>)>,
+    nodes: Query<(Option<&Opacity>, Option<&Children>)>,
+    changed: Query<Entity, Or<(Changed<Opacity>, Changed<Children>, Changed<Parent>)>>,
+    mut renders: Query<&mut AuiRender>,
+) {
+    // the tree is only walked again when opacities or its shape changed
+    if changed.iter().next().is_none()
+        && nodes.removed::<Opacity>().is_empty()
+        && nodes.removed::<Children>().is_empty()
+        && nodes.removed::<Parent>().is_empty()
+    {
+        return;
+    }
+
+    fn propagate(
+        entity: Entity,
+        parent_opacity: f32,
+        nodes: &Query<(Option<&Opacity>, Option<&Children>)>,
+        renders: &mut Query<&mut AuiRender>,
+    ) {
+        let (own, children) = match nodes.get(entity) {
+            Ok(node) => node,
+            Err(_) => return,
+        };
+        let opacity = parent_opacity * own.map_or(1., |opacity| opacity.0.max(0.).min(1.));
+        if let Ok(mut render_data) = renders.get_mut(entity) {
+            if render_data.opacity != opacity {
+                render_data.opacity = opacity;
+            }
+        }
+        // shadow quads are children too, so they fade with their node
+        if let Some(children) = children {
+            for child in children.iter() {
+                propagate(*child, opacity, nodes, renders);
+            }
+        }
+    }
+
+    for root in roots.iter() {
+        propagate(root, 1., &nodes, &mut renders);
+    }
+}
+
 pub struct AUIPlugin;
 
 const STAGE: &str = "aui";
+// Widgets write to their ANodes in here, so the layout picks the changes up in the same frame
+const WIDGET_STAGE: &str = "aui_widgets";
+// After visible entities are collected in POST_UPDATE, and before anything is drawn
+const TARGET_STAGE: &str = "aui_targets";
 impl Plugin for AUIPlugin {
     fn build(&self, app: &mut bevy_app::AppBuilder) {
-        app.add_stage_before(stage::POST_UPDATE, STAGE, SystemStage::parallel())
-            .add_system_to_stage(STAGE, layout_system.system());
+        app.init_resource::<LayoutDirection>()
+            .init_resource::<SafeArea>()
+            .init_resource::<UiTargets>()
+            .add_stage_before(stage::POST_UPDATE, STAGE, SystemStage::parallel())
+            .add_system_to_stage(STAGE, layout_system.system())
+            .add_system_to_stage(STAGE, draw_order_system.system())
+            .add_system_to_stage(STAGE, display_system.system())
+            .add_system_to_stage(STAGE, virtual_list_clip_system.system())
+            .add_system_to_stage(STAGE, opacity_system.system())
+            .add_system_to_stage(STAGE, fill_system.system())
+            .add_system_to_stage(STAGE, gradient_system.system())
+            .add_system_to_stage(STAGE, shadow_system.system())
+            .add_system_to_stage(STAGE, backdrop_system.system())
+            .add_system_to_stage(STAGE, atlas_image_system.system())
+            .add_system_to_stage(STAGE, image_scale_system.system())
+            .add_system_to_stage(STAGE, target_camera_system.system())
+            // after layout and display, so batches see this frame's rects and visibility
+            .add_system_to_stage(STAGE, batch_system.system())
+            .add_stage_before(STAGE, WIDGET_STAGE, SystemStage::parallel())
+            .add_stage_after(stage::POST_UPDATE, TARGET_STAGE, SystemStage::parallel())
+            .add_system_to_stage(TARGET_STAGE, target_visibility_system.system())
+            .init_resource::<UiCursor>()
+            .init_resource::<UiFocus>()
+            .init_resource::<UiClipboard>()
+            .init_resource::<UiBackdrop>()
+            .init_resource::<AtlasMaterials>()
+            .add_event::<Clicked>()
+            .add_event::<ValueChanged<f32>>()
+            .add_event::<ValueChanged<bool>>()
+            .add_event::<ValueChanged<String>>()
+            .add_event::<ValueChanged<usize>>()
+            .add_event::<TextSubmitted>()
+            .add_event::<TextComposition>()
+            .add_event::<DragEvent>()
+            .add_system_to_stage(stage::PRE_UPDATE, interaction_system.system())
+            .add_system_to_stage(stage::UPDATE, button_system.system())
+            .add_system_to_stage(stage::UPDATE, slider_system.system())
+            .add_system_to_stage(stage::UPDATE, checkbox_system.system())
+            .add_system_to_stage(stage::UPDATE, text_input_system.system())
+            .add_system_to_stage(stage::UPDATE, drag_system.system())
+            .add_system_to_stage(stage::UPDATE, splitter_system.system())
+            .add_system_to_stage(stage::UPDATE, tab_button_system.system())
+            .add_system_to_stage(stage::UPDATE, dropdown_system.system())
+            .add_system_to_stage(WIDGET_STAGE, slider_thumb_system.system())
+            .add_system_to_stage(WIDGET_STAGE, checkbox_mark_system.system())
+            .add_system_to_stage(WIDGET_STAGE, toggle_knob_system.system())
+            .add_system_to_stage(WIDGET_STAGE, text_input_visual_system.system())
+            .add_system_to_stage(WIDGET_STAGE, virtual_list_system.system())
+            .add_system_to_stage(WIDGET_STAGE, tab_page_system.system())
+            .add_system_to_stage(WIDGET_STAGE, tab_slide_system.system())
+            .add_system_to_stage(WIDGET_STAGE, dropdown_list_system.system())
+            .add_system_to_stage(WIDGET_STAGE, tooltip_system.system())
+            .add_system_to_stage(WIDGET_STAGE, atlas_animation_system.system())
+            .add_system_to_stage(WIDGET_STAGE, native_size_system.system());
 
         let resources = app.resources();
         resources
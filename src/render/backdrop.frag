@@ -0,0 +1,64 @@
+#version 450
+
+layout(set = 1, binding = 1) uniform AuiRender_size {
+    vec2 NodeSize;
+};
+layout(set = 1, binding = 2) uniform AuiRender_clip {
+    vec4 Clip;
+};
+layout(set = 1, binding = 3) uniform BackdropRender_rect {
+    // half size of the node, corner radius and blur radius in physical pixels
+    vec4 Rect;
+};
+layout(set = 1, binding = 11) uniform AuiRender_opacity {
+    float Opacity;
+};
+
+// The scene the main pass rendered, at the size of the window
+layout(set = 2, binding = 1) uniform texture2D ColorMaterial_texture;
+layout(set = 2, binding = 2) uniform sampler ColorMaterial_texture_sampler;
+
+layout(location = 0) in vec2 v_Uv;
+layout(location = 2) in vec2 v_Position;
+
+layout(location = 0) out vec4 o_Target;
+
+// Samples on each axis of the kernel, spread evenly over the blur radius
+const int TAPS = 9;
+
+float rounded_box(vec2 p, vec2 half_size, float radius) {
+    vec2 q = abs(p) - half_size + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+void main() {
+    if (any(lessThan(v_Position, Clip.xy)) || any(greaterThan(v_Position, Clip.zw))) {
+        discard;
+    }
+    vec2 p = (vec2(v_Uv.x, 1.0 - v_Uv.y) - 0.5) * NodeSize;
+    float d = rounded_box(p, Rect.xy, min(Rect.z, min(Rect.x, Rect.y)));
+    float coverage = 1.0 - smoothstep(-0.5, 0.5, d);
+    if (coverage <= 0.0) {
+        discard;
+    }
+
+    // the texture covers the whole window, so the fragment's pixel is also its texel
+    vec2 texel = 1.0 / vec2(textureSize(
+        sampler2D(ColorMaterial_texture, ColorMaterial_texture_sampler), 0));
+    vec2 uv = gl_FragCoord.xy * texel;
+    float spacing = Rect.w / float(TAPS / 2);
+    float sigma = max(Rect.w / 2.0, 0.001);
+    vec3 sum = vec3(0.0);
+    float total = 0.0;
+    for (int x = -TAPS / 2; x <= TAPS / 2; x++) {
+        for (int y = -TAPS / 2; y <= TAPS / 2; y++) {
+            vec2 offset = vec2(x, y) * spacing;
+            float weight = exp(-dot(offset, offset) / (2.0 * sigma * sigma));
+            sum += weight * texture(
+                sampler2D(ColorMaterial_texture, ColorMaterial_texture_sampler),
+                uv + offset * texel).rgb;
+            total += weight;
+        }
+    }
+    o_Target = vec4(sum / total, coverage * Opacity);
+}
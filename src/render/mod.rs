@@ -1,11 +1,15 @@
 // Taken directly from bevy_ui
 
-use crate::AuiRender;
-use bevy_asset::{Assets, HandleUntyped};
+mod texture_node;
+
+use crate::{AuiRender, BackdropRender, ShadowRender, TargetSurface, UiBackdrop, UiTargets};
+use bevy_asset::{Asset, Assets, Handle, HandleUntyped};
 use bevy_ecs::Resources;
+use bevy_math::Vec2;
 use bevy_reflect::TypeUuid;
 use bevy_render::{
     camera::ActiveCameras,
+    color::Color,
     pass::{
         LoadOp, Operations, PassDescriptor, RenderPassDepthStencilAttachmentDescriptor,
         TextureAttachment,
@@ -13,17 +17,79 @@ use bevy_render::{
     pipeline::*,
     prelude::Msaa,
     render_graph::{
-        base, CameraNode, PassNode, RenderGraph, RenderResourcesNode, WindowSwapChainNode,
-        WindowTextureNode,
+        base, AssetRenderResourcesNode, CameraNode, PassNode, RenderGraph, RenderResourcesNode,
+        WindowSwapChainNode, WindowTextureNode,
     },
+    renderer::RenderResources,
     shader::{Shader, ShaderStage, ShaderStages},
-    texture::TextureFormat,
+    texture::{
+        Extent3d, Texture, TextureDescriptor, TextureDimension, TextureFormat, TextureUsage,
+    },
 };
+use bevy_sprite::ColorMaterial;
+use bevy_window::WindowId;
+pub use texture_node::{UiTextureNode, UiWindowTextureNode};
 
 pub const UI_PIPELINE_HANDLE: HandleUntyped =
     HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 3234320022263998);
+pub const UI_BATCH_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 3234320022263999);
+pub const SHADOW_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 3234320022264000);
+pub const BACKDROP_PIPELINE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(PipelineDescriptor::TYPE_UUID, 3234320022264001);
+// What the main pass renders into once add_ui_backdrop took it off the window. No texture asset
+// exists for it, the render graph binds the texture to it directly.
+pub const BACKDROP_TEXTURE_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Texture::TYPE_UUID, 3234320022264002);
 
 pub fn build_ui_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
+    ui_pipeline(shaders, include_str!("ui.vert"), include_str!("ui.frag"))
+}
+
+// Batches carry their vertices in world space already, so only the vertex stage differs
+pub fn build_ui_batch_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
+    ui_pipeline(
+        shaders,
+        include_str!("ui_batch.vert"),
+        include_str!("ui.frag"),
+    )
+}
+
+// Shadows are quads like any node, shaded from a rounded box distance instead of a material
+pub fn build_shadow_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
+    ui_pipeline(
+        shaders,
+        include_str!("ui.vert"),
+        include_str!("shadow.frag"),
+    )
+}
+
+// Blurs the scene texture behind the node, ColorMaterial's texture is that scene
+pub fn build_backdrop_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
+    ui_pipeline(
+        shaders,
+        include_str!("ui.vert"),
+        include_str!("backdrop.frag"),
+    )
+}
+
+// A pipeline for nodes with a material of their own. Without a vertex shader the default one is
+// used, which passes v_Uv on like for any node. Either way, set 1 has the node's Transform and
+// AuiRender bindings, and the material's should go in set 2 like ColorMaterial's do. The default
+// vertex shader passes the UI space position on as v_Position (location 2), to discard fragments
+// outside of AuiRender_clip (set 1, binding 2, min and max corners) with. Opacity isn't
+// applied for the fragment shader, it has to read AuiRender_opacity (set 1, binding 11, a float)
+// and multiply its alpha by it.
+pub fn build_ui_material_pipeline(
+    shaders: &mut Assets<Shader>,
+    vertex: Option<&str>,
+    fragment: &str,
+) -> PipelineDescriptor {
+    ui_pipeline(shaders, vertex.unwrap_or(include_str!("ui.vert")), fragment)
+}
+
+fn ui_pipeline(shaders: &mut Assets<Shader>, vertex: &str, fragment: &str) -> PipelineDescriptor {
     PipelineDescriptor {
         rasterization_state: Some(RasterizationStateDescriptor {
             front_face: FrontFace::Ccw,
@@ -35,8 +101,9 @@ pub fn build_ui_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
         }),
         depth_stencil_state: Some(DepthStencilStateDescriptor {
             format: TextureFormat::Depth32Float,
-            depth_write_enabled: true,
-            depth_compare: CompareFunction::Less,
+            // draw order alone decides what ends up in front, see draw_order_system
+            depth_write_enabled: false,
+            depth_compare: CompareFunction::Always,
             stencil: StencilStateDescriptor {
                 front: StencilStateFaceDescriptor::IGNORE,
                 back: StencilStateFaceDescriptor::IGNORE,
@@ -59,14 +126,8 @@ pub fn build_ui_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
             write_mask: ColorWrite::ALL,
         }],
         ..PipelineDescriptor::new(ShaderStages {
-            vertex: shaders.add(Shader::from_glsl(
-                ShaderStage::Vertex,
-                include_str!("ui.vert"),
-            )),
-            fragment: Some(shaders.add(Shader::from_glsl(
-                ShaderStage::Fragment,
-                include_str!("ui.frag"),
-            ))),
+            vertex: shaders.add(Shader::from_glsl(ShaderStage::Vertex, vertex)),
+            fragment: Some(shaders.add(Shader::from_glsl(ShaderStage::Fragment, fragment))),
         })
     }
 }
@@ -74,15 +135,62 @@ pub fn build_ui_pipeline(shaders: &mut Assets<Shader>) -> PipelineDescriptor {
 pub mod node {
     pub const CAMERA_UI: &str = "camera_aui";
     pub const NODE: &str = "anode";
+    pub const SHADOW: &str = "anode_shadow";
+    pub const BACKDROP: &str = "anode_backdrop";
+    pub const BACKDROP_TEXTURE: &str = "aui_backdrop_texture";
     pub const UI_PASS: &str = "aui_pass";
+    pub const UI_DEPTH: &str = "aui_depth";
 }
 
 pub mod camera {
     pub const CAMERA_UI: &str = "CameraAui";
 }
 
+fn ui_pass(msaa: &Msaa, load: LoadOp<Color>) -> PassNode<&'static AuiRender> {
+    PassNode::<&AuiRender>::new(PassDescriptor {
+        color_attachments: vec![msaa.color_attachment_descriptor(
+            TextureAttachment::Input("color_attachment".to_string()),
+            TextureAttachment::Input("color_resolve_target".to_string()),
+            Operations { load, store: true },
+        )],
+        depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
+            attachment: TextureAttachment::Input("depth".to_string()),
+            depth_ops: Some(Operations {
+                load: LoadOp::Clear(1.0),
+                store: true,
+            }),
+            stencil_ops: None,
+        }),
+        sample_count: msaa.samples,
+    })
+}
+
 pub trait UiRenderGraphBuilder {
     fn add_ui_graph(&mut self, resources: &Resources) -> &mut Self;
+    // Renders the main pass into a texture instead of the primary window, for BackdropBlur to
+    // sample. The ui pass draws that texture into the window before any node.
+    fn add_ui_backdrop(&mut self, resources: &Resources) -> &mut Self;
+    // Adds a pass rendering the roots with a UiTexture for `camera` into `texture`, which is
+    // created at `size`. The camera itself still has to be spawned, see
+    // AUiCameraBundle::for_texture.
+    fn add_ui_texture_target(
+        &mut self,
+        resources: &Resources,
+        camera: &str,
+        size: Vec2,
+        texture: Handle<Texture>,
+    ) -> &mut Self;
+    // Makes materials of type `M` available to nodes with custom pipelines, `name` has to be unique
+    // in the render graph. The asset type still has to be added to the app.
+    fn add_ui_material<M: Asset + RenderResources>(&mut self, name: &'static str) -> &mut Self;
+    // Adds a pass rendering the roots with a UiWindow for `camera` into `window`, which must not
+    // have a swap chain node of its own yet. See AUiCameraBundle::for_window.
+    fn add_ui_window_target(
+        &mut self,
+        resources: &Resources,
+        camera: &str,
+        window: WindowId,
+    ) -> &mut Self;
 }
 
 impl UiRenderGraphBuilder for RenderGraph {
@@ -91,27 +199,17 @@ impl UiRenderGraphBuilder for RenderGraph {
         let mut shaders = resources.get_mut::<Assets<Shader>>().unwrap();
         let msaa = resources.get::<Msaa>().unwrap();
         pipelines.set_untracked(UI_PIPELINE_HANDLE, build_ui_pipeline(&mut shaders));
+        pipelines.set_untracked(
+            UI_BATCH_PIPELINE_HANDLE,
+            build_ui_batch_pipeline(&mut shaders),
+        );
+        pipelines.set_untracked(SHADOW_PIPELINE_HANDLE, build_shadow_pipeline(&mut shaders));
+        pipelines.set_untracked(
+            BACKDROP_PIPELINE_HANDLE,
+            build_backdrop_pipeline(&mut shaders),
+        );
 
-        let mut ui_pass_node = PassNode::<&AuiRender>::new(PassDescriptor {
-            color_attachments: vec![msaa.color_attachment_descriptor(
-                TextureAttachment::Input("color_attachment".to_string()),
-                TextureAttachment::Input("color_resolve_target".to_string()),
-                Operations {
-                    load: LoadOp::Load,
-                    store: true,
-                },
-            )],
-            depth_stencil_attachment: Some(RenderPassDepthStencilAttachmentDescriptor {
-                attachment: TextureAttachment::Input("depth".to_string()),
-                depth_ops: Some(Operations {
-                    load: LoadOp::Clear(1.0),
-                    store: true,
-                }),
-                stencil_ops: None,
-            }),
-            sample_count: msaa.samples,
-        });
-
+        let mut ui_pass_node = ui_pass(&msaa, LoadOp::Load);
         ui_pass_node.add_camera(camera::CAMERA_UI);
         self.add_node(node::UI_PASS, ui_pass_node);
 
@@ -127,8 +225,21 @@ impl UiRenderGraphBuilder for RenderGraph {
         )
         .unwrap();
 
+        // the ui pass has a depth texture of its own, so the main pass's depth is left alone
+        self.add_node(
+            node::UI_DEPTH,
+            WindowTextureNode::new(
+                WindowId::primary(),
+                TextureDescriptor {
+                    sample_count: msaa.samples,
+                    format: TextureFormat::Depth32Float,
+                    usage: TextureUsage::OUTPUT_ATTACHMENT,
+                    ..Default::default()
+                },
+            ),
+        );
         self.add_slot_edge(
-            base::node::MAIN_DEPTH_TEXTURE,
+            node::UI_DEPTH,
             WindowTextureNode::OUT_TEXTURE,
             node::UI_PASS,
             "depth",
@@ -154,8 +265,250 @@ impl UiRenderGraphBuilder for RenderGraph {
         self.add_node_edge(node::CAMERA_UI, node::UI_PASS).unwrap();
         self.add_system_node(node::NODE, RenderResourcesNode::<AuiRender>::new(true));
         self.add_node_edge(node::NODE, node::UI_PASS).unwrap();
+        self.add_system_node(node::SHADOW, RenderResourcesNode::<ShadowRender>::new(true));
+        self.add_node_edge(node::SHADOW, node::UI_PASS).unwrap();
+        self.add_system_node(
+            node::BACKDROP,
+            RenderResourcesNode::<BackdropRender>::new(true),
+        );
+        self.add_node_edge(node::BACKDROP, node::UI_PASS).unwrap();
         let mut active_cameras = resources.get_mut::<ActiveCameras>().unwrap();
         active_cameras.add(camera::CAMERA_UI);
         self
     }
+
+    fn add_ui_backdrop(&mut self, resources: &Resources) -> &mut Self {
+        let msaa = resources.get::<Msaa>().unwrap();
+        // the slot the window's texture is bound to, like in bevy's base graph
+        let main_slot = if msaa.samples > 1 {
+            "color_resolve_target"
+        } else {
+            "color_attachment"
+        };
+        self.remove_slot_edge(
+            base::node::PRIMARY_SWAP_CHAIN,
+            WindowSwapChainNode::OUT_TEXTURE,
+            base::node::MAIN_PASS,
+            main_slot,
+        )
+        .unwrap();
+        self.add_node(
+            node::BACKDROP_TEXTURE,
+            UiWindowTextureNode::new(
+                WindowId::primary(),
+                TextureDescriptor {
+                    format: TextureFormat::default(),
+                    usage: TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::SAMPLED,
+                    ..Default::default()
+                },
+                BACKDROP_TEXTURE_HANDLE,
+            ),
+        );
+        self.add_slot_edge(
+            node::BACKDROP_TEXTURE,
+            UiWindowTextureNode::TEXTURE,
+            base::node::MAIN_PASS,
+            main_slot,
+        )
+        .unwrap();
+
+        let material = resources
+            .get_mut::<Assets<ColorMaterial>>()
+            .unwrap()
+            .add(ColorMaterial::texture(BACKDROP_TEXTURE_HANDLE.typed()));
+        resources.get_mut::<UiBackdrop>().unwrap().material = Some(material);
+        self
+    }
+
+    fn add_ui_material<M: Asset + RenderResources>(&mut self, name: &'static str) -> &mut Self {
+        self.add_system_node(name, AssetRenderResourcesNode::<M>::new(true));
+        // every ui pass runs after the main pass
+        self.add_node_edge(name, base::node::MAIN_PASS).unwrap();
+        self
+    }
+
+    fn add_ui_texture_target(
+        &mut self,
+        resources: &Resources,
+        camera: &str,
+        size: Vec2,
+        texture: Handle<Texture>,
+    ) -> &mut Self {
+        let msaa = resources.get::<Msaa>().unwrap();
+        let pass = format!("{}_pass", camera);
+        let color = format!("{}_color", camera);
+        let depth = format!("{}_depth", camera);
+        let descriptor = |format, sample_count, usage| TextureDescriptor {
+            size: Extent3d::new(size.x as u32, size.y as u32, 1),
+            mip_level_count: 1,
+            sample_count,
+            dimension: TextureDimension::D2,
+            format,
+            usage,
+        };
+
+        let mut pass_node = ui_pass(&msaa, LoadOp::Clear(Color::rgba(0., 0., 0., 0.)));
+        pass_node.add_camera(camera);
+        self.add_node(pass.clone(), pass_node);
+
+        // with msaa the pass renders into a multisampled texture and resolves into the target one
+        self.add_node(
+            color.clone(),
+            UiTextureNode::new(
+                descriptor(
+                    TextureFormat::default(),
+                    1,
+                    TextureUsage::OUTPUT_ATTACHMENT | TextureUsage::SAMPLED,
+                ),
+                Some(texture.clone_untyped()),
+            ),
+        );
+        self.add_slot_edge(
+            color.clone(),
+            UiTextureNode::TEXTURE,
+            pass.clone(),
+            if msaa.samples > 1 {
+                "color_resolve_target"
+            } else {
+                "color_attachment"
+            },
+        )
+        .unwrap();
+        if msaa.samples > 1 {
+            let sampled = format!("{}_sampled_color", camera);
+            self.add_node(
+                sampled.clone(),
+                UiTextureNode::new(
+                    descriptor(
+                        TextureFormat::default(),
+                        msaa.samples,
+                        TextureUsage::OUTPUT_ATTACHMENT,
+                    ),
+                    None,
+                ),
+            );
+            self.add_slot_edge(
+                sampled,
+                UiTextureNode::TEXTURE,
+                pass.clone(),
+                "color_attachment",
+            )
+            .unwrap();
+        }
+        self.add_node(
+            depth.clone(),
+            UiTextureNode::new(
+                descriptor(
+                    TextureFormat::Depth32Float,
+                    msaa.samples,
+                    TextureUsage::OUTPUT_ATTACHMENT,
+                ),
+                None,
+            ),
+        );
+        self.add_slot_edge(depth, UiTextureNode::TEXTURE, pass.clone(), "depth")
+            .unwrap();
+
+        // same ordering as the window ui pass, materials and transforms are ready by then
+        self.add_node_edge(base::node::MAIN_PASS, pass.clone())
+            .unwrap();
+        self.add_system_node(camera, CameraNode::new(camera));
+        self.add_node_edge(camera, pass.clone()).unwrap();
+        self.add_node_edge(node::NODE, pass.clone()).unwrap();
+        self.add_node_edge(node::SHADOW, pass.clone()).unwrap();
+        self.add_node_edge(node::BACKDROP, pass).unwrap();
+
+        let mut active_cameras = resources.get_mut::<ActiveCameras>().unwrap();
+        active_cameras.add(camera);
+        resources
+            .get_mut::<UiTargets>()
+            .unwrap()
+            .targets
+            .push((camera.to_string(), TargetSurface::Texture(size)));
+        self
+    }
+
+    fn add_ui_window_target(
+        &mut self,
+        resources: &Resources,
+        camera: &str,
+        window: WindowId,
+    ) -> &mut Self {
+        let msaa = resources.get::<Msaa>().unwrap();
+        let pass = format!("{}_pass", camera);
+        let swap_chain = format!("{}_swap_chain", camera);
+        let depth = format!("{}_depth", camera);
+
+        // nothing else draws into the window, so this pass clears it
+        let mut pass_node = ui_pass(&msaa, LoadOp::Clear(Color::default()));
+        pass_node.use_default_clear_color(0);
+        pass_node.add_camera(camera);
+        self.add_node(pass.clone(), pass_node);
+
+        self.add_node(swap_chain.clone(), WindowSwapChainNode::new(window));
+        self.add_slot_edge(
+            swap_chain,
+            WindowSwapChainNode::OUT_TEXTURE,
+            pass.clone(),
+            if msaa.samples > 1 {
+                "color_resolve_target"
+            } else {
+                "color_attachment"
+            },
+        )
+        .unwrap();
+        if msaa.samples > 1 {
+            let sampled = format!("{}_sampled_color", camera);
+            self.add_node(
+                sampled.clone(),
+                WindowTextureNode::new(
+                    window,
+                    TextureDescriptor {
+                        sample_count: msaa.samples,
+                        format: TextureFormat::default(),
+                        usage: TextureUsage::OUTPUT_ATTACHMENT,
+                        ..Default::default()
+                    },
+                ),
+            );
+            self.add_slot_edge(
+                sampled,
+                WindowTextureNode::OUT_TEXTURE,
+                pass.clone(),
+                "color_attachment",
+            )
+            .unwrap();
+        }
+        self.add_node(
+            depth.clone(),
+            WindowTextureNode::new(
+                window,
+                TextureDescriptor {
+                    sample_count: msaa.samples,
+                    format: TextureFormat::Depth32Float,
+                    usage: TextureUsage::OUTPUT_ATTACHMENT,
+                    ..Default::default()
+                },
+            ),
+        );
+        self.add_slot_edge(depth, WindowTextureNode::OUT_TEXTURE, pass.clone(), "depth")
+            .unwrap();
+
+        self.add_node_edge(base::node::MAIN_PASS, pass.clone())
+            .unwrap();
+        self.add_system_node(camera, CameraNode::new(camera));
+        self.add_node_edge(camera, pass.clone()).unwrap();
+        self.add_node_edge(node::NODE, pass.clone()).unwrap();
+        self.add_node_edge(node::SHADOW, pass.clone()).unwrap();
+        self.add_node_edge(node::BACKDROP, pass).unwrap();
+
+        let mut active_cameras = resources.get_mut::<ActiveCameras>().unwrap();
+        active_cameras.add(camera);
+        resources
+            .get_mut::<UiTargets>()
+            .unwrap()
+            .targets
+            .push((camera.to_string(), TargetSurface::Window(window)));
+        self
+    }
 }
@@ -0,0 +1,41 @@
+#version 450
+
+layout(set = 1, binding = 1) uniform AuiRender_size {
+    vec2 NodeSize;
+};
+layout(set = 1, binding = 2) uniform AuiRender_clip {
+    vec4 Clip;
+};
+layout(set = 1, binding = 3) uniform ShadowRender_rect {
+    // half size of the shadowed box, corner radius and blur
+    vec4 Rect;
+};
+layout(set = 1, binding = 4) uniform ShadowRender_color {
+    vec4 Color;
+};
+layout(set = 1, binding = 11) uniform AuiRender_opacity {
+    float Opacity;
+};
+
+layout(location = 0) in vec2 v_Uv;
+layout(location = 2) in vec2 v_Position;
+
+layout(location = 0) out vec4 o_Target;
+
+float rounded_box(vec2 p, vec2 half_size, float radius) {
+    vec2 q = abs(p) - half_size + radius;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - radius;
+}
+
+void main() {
+    if (any(lessThan(v_Position, Clip.xy)) || any(greaterThan(v_Position, Clip.zw))) {
+        discard;
+    }
+    // the quad is centered on the shadow, with room around the box for the blur
+    vec2 p = (vec2(v_Uv.x, 1.0 - v_Uv.y) - 0.5) * NodeSize;
+    float radius = min(Rect.z, min(Rect.x, Rect.y));
+    float d = rounded_box(p, Rect.xy, radius);
+    // at least half a pixel, so hard shadows still get antialiased edges
+    float blur = max(Rect.w, 0.5);
+    o_Target = vec4(Color.rgb, Color.a * Opacity * (1.0 - smoothstep(-blur, blur, d)));
+}
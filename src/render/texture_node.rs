@@ -0,0 +1,155 @@
+use std::borrow::Cow;
+
+use bevy_app::{EventReader, Events};
+use bevy_asset::HandleUntyped;
+use bevy_ecs::{Resources, World};
+use bevy_render::{
+    render_graph::{Node, ResourceSlotInfo, ResourceSlots},
+    renderer::{
+        RenderContext, RenderResourceContext, RenderResourceId, RenderResourceType, SamplerId,
+        TextureId,
+    },
+    texture::{SamplerDescriptor, TextureDescriptor, SAMPLER_ASSET_INDEX, TEXTURE_ASSET_INDEX},
+};
+use bevy_window::{WindowCreated, WindowId, WindowResized, Windows};
+
+fn bind_to_handle(
+    resources: &dyn RenderResourceContext,
+    handle: &HandleUntyped,
+    texture: TextureId,
+    sampler: SamplerId,
+) {
+    resources.set_asset_resource_untyped(
+        handle.clone(),
+        RenderResourceId::Texture(texture),
+        TEXTURE_ASSET_INDEX,
+    );
+    resources.set_asset_resource_untyped(
+        handle.clone(),
+        RenderResourceId::Sampler(sampler),
+        SAMPLER_ASSET_INDEX,
+    );
+}
+
+// Like bevy's TextureNode, but the texture can also be bound to a texture asset handle, so
+// materials using that handle sample whatever the ui pass rendered into it
+pub struct UiTextureNode {
+    descriptor: TextureDescriptor,
+    handle: Option<HandleUntyped>,
+}
+
+impl UiTextureNode {
+    pub const TEXTURE: &'static str = "texture";
+
+    pub fn new(descriptor: TextureDescriptor, handle: Option<HandleUntyped>) -> Self {
+        Self { descriptor, handle }
+    }
+}
+
+impl Node for UiTextureNode {
+    fn output(&self) -> &[ResourceSlotInfo] {
+        static OUTPUT: &[ResourceSlotInfo] = &[ResourceSlotInfo {
+            name: Cow::Borrowed(UiTextureNode::TEXTURE),
+            resource_type: RenderResourceType::Texture,
+        }];
+        OUTPUT
+    }
+
+    fn update(
+        &mut self,
+        _world: &World,
+        _resources: &Resources,
+        render_context: &mut dyn RenderContext,
+        _input: &ResourceSlots,
+        output: &mut ResourceSlots,
+    ) {
+        if output.get(0).is_some() {
+            return;
+        }
+        let resources = render_context.resources_mut();
+        let texture = resources.create_texture(self.descriptor);
+        if let Some(handle) = &self.handle {
+            let sampler = resources.create_sampler(&SamplerDescriptor::default());
+            bind_to_handle(resources, handle, texture, sampler);
+        }
+        output.set(0, RenderResourceId::Texture(texture));
+    }
+}
+
+// Like bevy's WindowTextureNode, but bound to a texture asset handle like UiTextureNode. The
+// texture is made again whenever the window is resized, so materials using the handle have to be
+// marked modified afterwards to pick the new one up.
+pub struct UiWindowTextureNode {
+    window: WindowId,
+    descriptor: TextureDescriptor,
+    handle: HandleUntyped,
+    sampler: Option<SamplerId>,
+    created: EventReader<WindowCreated>,
+    resized: EventReader<WindowResized>,
+}
+
+impl UiWindowTextureNode {
+    pub const TEXTURE: &'static str = "texture";
+
+    pub fn new(window: WindowId, descriptor: TextureDescriptor, handle: HandleUntyped) -> Self {
+        Self {
+            window,
+            descriptor,
+            handle,
+            sampler: None,
+            created: Default::default(),
+            resized: Default::default(),
+        }
+    }
+}
+
+impl Node for UiWindowTextureNode {
+    fn output(&self) -> &[ResourceSlotInfo] {
+        static OUTPUT: &[ResourceSlotInfo] = &[ResourceSlotInfo {
+            name: Cow::Borrowed(UiWindowTextureNode::TEXTURE),
+            resource_type: RenderResourceType::Texture,
+        }];
+        OUTPUT
+    }
+
+    fn update(
+        &mut self,
+        _world: &World,
+        resources: &Resources,
+        render_context: &mut dyn RenderContext,
+        _input: &ResourceSlots,
+        output: &mut ResourceSlots,
+    ) {
+        let windows = resources.get::<Windows>().unwrap();
+        let window = match windows.get(self.window) {
+            Some(window) => window,
+            None => return,
+        };
+        let created_events = resources.get::<Events<WindowCreated>>().unwrap();
+        let resized_events = resources.get::<Events<WindowResized>>().unwrap();
+        let created = self
+            .created
+            .find_latest(&created_events, |event| event.id == window.id())
+            .is_some();
+        let resized = self
+            .resized
+            .find_latest(&resized_events, |event| event.id == window.id())
+            .is_some();
+        if !created && !resized && output.get(0).is_some() {
+            return;
+        }
+
+        let render_resources = render_context.resources_mut();
+        if let Some(RenderResourceId::Texture(old)) = output.get(0) {
+            render_resources.remove_texture(old);
+        }
+        self.descriptor.size.width = window.physical_width();
+        self.descriptor.size.height = window.physical_height();
+        let texture = render_resources.create_texture(self.descriptor);
+        let sampler = *self
+            .sampler
+            .get_or_insert_with(|| render_resources.create_sampler(&SamplerDescriptor::default()));
+        bind_to_handle(render_resources, &self.handle, texture, sampler);
+        output.set(0, RenderResourceId::Texture(texture));
+    }
+}
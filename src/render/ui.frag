@@ -2,6 +2,41 @@
 
 // Taken from bevy_ui
 
+layout(set = 1, binding = 1) uniform AuiRender_size {
+    vec2 NodeSize;
+};
+layout(set = 1, binding = 2) uniform AuiRender_clip {
+    // min and max corners in UI space
+    vec4 Clip;
+};
+layout(set = 1, binding = 3) uniform AuiRender_fill {
+    // amount, mode, reversed
+    vec4 Fill;
+};
+layout(set = 1, binding = 4) uniform AuiRender_gradient {
+    // shape, stop count, space
+    vec4 Gradient;
+};
+layout(set = 1, binding = 5) uniform AuiRender_gradient_shape {
+    vec4 GradientShape;
+};
+layout(set = 1, binding = 6) uniform AuiRender_gradient_stops {
+    vec4 GradientStops;
+};
+layout(set = 1, binding = 7) uniform AuiRender_gradient_colors {
+    mat4 GradientColors;
+};
+layout(set = 1, binding = 8) uniform AuiRender_uv_rect {
+    // offset and scale
+    vec4 UvRect;
+};
+layout(set = 1, binding = 10) uniform AuiRender_image_tiled {
+    float ImageTiled;
+};
+layout(set = 1, binding = 11) uniform AuiRender_opacity {
+    float Opacity;
+};
+
 layout(set = 2, binding = 0) uniform ColorMaterial_color {
     vec4 Color;
 };
@@ -12,15 +47,120 @@ layout(set = 2, binding = 2) uniform sampler ColorMaterial_texture_sampler;
 # endif
 
 layout(location = 0) in vec2 v_Uv;
+layout(location = 1) in vec2 v_TexUv;
+layout(location = 2) in vec2 v_Position;
 
 layout(location = 0) out vec4 o_Target;
 
+const float TAU = 6.28318530718;
+
+// How far along the fill this fragment is, from 0 to 1
+float fill_progress(vec2 uv, int mode, bool reversed) {
+    // uvs start at the top left, so flip y to have it point up
+    vec2 p = vec2(uv.x, 1.0 - uv.y);
+    float progress = 0.0;
+    if (mode == 1) {
+        progress = p.x;
+    } else if (mode == 2) {
+        progress = p.y;
+    } else if (mode == 3) {
+        progress = length(p - 0.5) / length(vec2(0.5));
+    } else if (mode == 4) {
+        vec2 d = p - 0.5;
+        if (reversed) {
+            d.x = -d.x;
+        }
+        return mod(atan(d.x, d.y) / TAU, 1.0);
+    }
+    return reversed ? 1.0 - progress : progress;
+}
+
+vec3 to_srgb(vec3 c) {
+    return mix(c * 12.92, 1.055 * pow(c, vec3(1.0 / 2.4)) - 0.055, step(0.0031308, c));
+}
+
+vec3 to_linear(vec3 c) {
+    return mix(c / 12.92, pow((c + 0.055) / 1.055, vec3(2.4)), step(0.04045, c));
+}
+
+// Colors arrive linear, srgb gradients are blended after converting them back
+vec4 blend(vec4 a, vec4 b, float t, bool srgb) {
+    if (srgb) {
+        vec3 c = mix(to_srgb(a.rgb), to_srgb(b.rgb), t);
+        return vec4(to_linear(c), mix(a.a, b.a, t));
+    }
+    return mix(a, b, t);
+}
+
+vec4 gradient_color(vec2 uv, int shape, int count, bool srgb) {
+    vec2 p = vec2(uv.x, 1.0 - uv.y);
+    if (shape == 3) {
+        return blend(
+            blend(GradientColors[0], GradientColors[1], p.x, srgb),
+            blend(GradientColors[2], GradientColors[3], p.x, srgb),
+            p.y,
+            srgb);
+    }
+
+    float t = 0.0;
+    if (shape == 1) {
+        // the gradient spans the node's extent along its direction, corner to corner
+        vec2 dir = vec2(cos(GradientShape.x), sin(GradientShape.x));
+        float extent = dot(abs(dir), NodeSize);
+        t = dot((p - 0.5) * NodeSize, dir) / max(extent, 0.0001) + 0.5;
+    } else if (shape == 2) {
+        t = length((p - GradientShape.xy) * NodeSize) / max(GradientShape.z, 0.0001);
+    }
+
+    vec4 color = GradientColors[0];
+    for (int i = 1; i < count; i++) {
+        float from = GradientStops[i - 1];
+        float to = GradientStops[i];
+        if (t > from) {
+            color = blend(
+                GradientColors[i - 1],
+                GradientColors[i],
+                clamp((t - from) / max(to - from, 0.0001), 0.0, 1.0),
+                srgb);
+        }
+    }
+    return color;
+}
+
 void main() {
+    if (any(lessThan(v_Position, Clip.xy)) || any(greaterThan(v_Position, Clip.zw))) {
+        discard;
+    }
+    int fill_mode = int(Fill.y + 0.5);
+    if (fill_mode != 0 && fill_progress(v_Uv, fill_mode, Fill.z > 0.5) > Fill.x) {
+        discard;
+    }
+
     vec4 color = Color;
+    int gradient_shape = int(Gradient.x + 0.5);
+    if (gradient_shape != 0) {
+        color *= gradient_color(v_Uv, gradient_shape, int(Gradient.y + 0.5), Gradient.z > 0.5);
+    }
     # ifdef COLORMATERIAL_TEXTURE
+        vec2 image_uv = v_TexUv;
+        if (ImageTiled > 0.5) {
+            image_uv = fract(image_uv);
+        } else if (any(lessThan(image_uv, vec2(0.0))) || any(greaterThan(image_uv, vec2(1.0)))) {
+            // letterboxed
+            discard;
+        }
+        // kept half a texel inside the region, so filtering at the edges of atlas regions and
+        // tile seams doesn't pick up neighbouring texels
+        vec2 half_texel = 0.5 / vec2(textureSize(
+            sampler2D(ColorMaterial_texture, ColorMaterial_texture_sampler), 0));
+        // flipped images have a negative scale
+        vec2 inset = min(half_texel, abs(UvRect.zw) * 0.5);
+        vec2 region_min = min(UvRect.xy, UvRect.xy + UvRect.zw) + inset;
+        vec2 region_max = max(UvRect.xy, UvRect.xy + UvRect.zw) - inset;
         color *= texture(
             sampler2D(ColorMaterial_texture, ColorMaterial_texture_sampler),
-            v_Uv);
+            clamp(UvRect.xy + image_uv * UvRect.zw, region_min, region_max));
     # endif
+    color.a *= Opacity;
     o_Target = color;//vec4(1.0, 0.0, 0.0, 0.3);
-}
\ No newline at end of file
+}
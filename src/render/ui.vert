@@ -7,6 +7,10 @@ layout(location = 1) in vec3 Vertex_Normal;
 layout(location = 2) in vec2 Vertex_Uv;
 
 layout(location = 0) out vec2 v_Uv;
+// Coordinates into the image, before it's tiled and mapped into the texture
+layout(location = 1) out vec2 v_TexUv;
+// Position in UI space, for clipping
+layout(location = 2) out vec2 v_Position;
 
 layout(set = 0, binding = 0) uniform Camera {
     mat4 ViewProj;
@@ -18,9 +22,16 @@ layout(set = 1, binding = 0) uniform Transform {
 layout(set = 1, binding = 1) uniform AuiRender_size {
     vec2 NodeSize;
 };
+layout(set = 1, binding = 9) uniform AuiRender_image_rect {
+    // offset and scale
+    vec4 ImageRect;
+};
 
 void main() {
     v_Uv = Vertex_Uv;
+    v_TexUv = ImageRect.xy + Vertex_Uv * ImageRect.zw;
     vec3 position = Vertex_Position * vec3(NodeSize, 0.0);
-    gl_Position = ViewProj * Object * vec4(position, 1.0);
+    vec4 world = Object * vec4(position, 1.0);
+    v_Position = world.xy;
+    gl_Position = ViewProj * world;
 }
\ No newline at end of file
@@ -0,0 +1,28 @@
+#version 450
+
+// Vertices of every node in the batch, already transformed and sized by the batch system, with
+// the nodes' image rects applied to their uvs
+
+layout(location = 0) in vec3 Vertex_Position;
+layout(location = 1) in vec3 Vertex_Normal;
+layout(location = 2) in vec2 Vertex_Uv;
+
+layout(location = 0) out vec2 v_Uv;
+layout(location = 1) out vec2 v_TexUv;
+layout(location = 2) out vec2 v_Position;
+
+layout(set = 0, binding = 0) uniform Camera {
+    mat4 ViewProj;
+};
+
+layout(set = 1, binding = 0) uniform Transform {
+    mat4 Object;
+};
+
+void main() {
+    v_Uv = Vertex_Uv;
+    v_TexUv = Vertex_Uv;
+    vec4 world = Object * vec4(Vertex_Position, 1.0);
+    v_Position = world.xy;
+    gl_Position = ViewProj * world;
+}
@@ -0,0 +1,304 @@
+use bevy_asset::{Assets, Handle};
+use bevy_ecs::{Commands, Entity, Local, Query, Res, ResMut, With, Without};
+use bevy_math::{Vec2, Vec4};
+use bevy_reflect::Reflect;
+use bevy_render::{
+    color::Color,
+    mesh::Mesh,
+    pipeline::RenderPipeline,
+    prelude::{Draw, RenderPipelines, Visible},
+    renderer::RenderResources,
+};
+use bevy_sprite::{ColorMaterial, QUAD_HANDLE};
+use bevy_transform::{
+    components::{Children, GlobalTransform, Parent, Transform},
+    hierarchy::{BuildChildren, DespawnRecursiveExt},
+};
+use bevy_window::Windows;
+
+use crate::{
+    render::{BACKDROP_PIPELINE_HANDLE, SHADOW_PIPELINE_HANDLE, UI_PIPELINE_HANDLE},
+    AuiRender, PassThrough, UiTargets,
+};
+
+// Half a DRAW_ORDER_STEP behind the node, so the shadow is drawn right before it
+pub const SHADOW_Z: f32 = -0.0005;
+
+// Draws a soft shadow behind the node: a box of the node's size grown by `spread` on each side,
+// moved by `offset`, with rounded corners, and faded out over `blur` units around its edge.
+#[derive(Clone, Debug)]
+pub struct Shadow {
+    pub offset: Vec2,
+    pub blur: f32,
+    pub spread: f32,
+    pub corner_radius: f32,
+    pub color: Color,
+}
+
+impl Default for Shadow {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::new(0., -4.),
+            blur: 8.,
+            spread: 0.,
+            corner_radius: 0.,
+            color: Color::rgba(0., 0., 0., 0.5),
+        }
+    }
+}
+
+// The child entity a Shadow is drawn with, it has no ANode so layout skips it
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ShadowQuad;
+
+#[derive(Default, RenderResources, Reflect, Clone, Debug)]
+pub struct ShadowRender {
+    // half size of the box, corner radius and blur
+    pub rect: Vec4,
+    pub color: Color,
+}
+
+pub fn shadow_system(
+    commands: &mut Commands,
+    shadows: Query<(Entity, &Shadow, &AuiRender, Option<&Children>), Without<ShadowQuad>>,
+    mut quads: Query<(&mut Transform, &mut AuiRender, &mut ShadowRender), With<ShadowQuad>>,
+    quad_parents: Query<(Entity, &Parent), With<ShadowQuad>>,
+) {
+    // quads of nodes that lost their Shadow
+    for (quad, parent) in quad_parents.iter() {
+        if shadows.get_component::<Shadow>(parent.0).is_err() {
+            commands.despawn_recursive(quad);
+        }
+    }
+
+    for (entity, shadow, render, children) in shadows.iter() {
+        let size = render.size + Vec2::splat(2. * (shadow.spread + shadow.blur));
+        let translation = shadow.offset.extend(SHADOW_Z);
+        let half = render.size / 2. + Vec2::splat(shadow.spread);
+        let shadow_render = ShadowRender {
+            rect: Vec4::new(
+                half.x,
+                half.y,
+                shadow.corner_radius + shadow.spread,
+                shadow.blur,
+            ),
+            color: shadow.color,
+        };
+
+        let quad = children
+            .iter()
+            .flat_map(|children| children.iter())
+            .find(|child| quads.get_component::<ShadowQuad>(**child).is_ok());
+        match quad {
+            Some(quad) => {
+                let (mut transform, mut quad_render, mut quad_shadow) =
+                    quads.get_mut(*quad).unwrap();
+                if transform.translation != translation {
+                    transform.translation = translation;
+                }
+                if quad_render.size != size {
+                    quad_render.size = size;
+                }
+                if quad_shadow.rect != shadow_render.rect || quad_shadow.color != shadow.color {
+                    *quad_shadow = shadow_render;
+                }
+            }
+            None => {
+                let quad = commands
+                    .spawn((
+                        QUAD_HANDLE.typed::<Mesh>(),
+                        Draw::default(),
+                        Visible {
+                            is_transparent: true,
+                            ..Default::default()
+                        },
+                        RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                            SHADOW_PIPELINE_HANDLE.typed(),
+                        )]),
+                        Transform::from_translation(translation),
+                        GlobalTransform::default(),
+                        // opacity_system keeps this up to date from the next frame on
+                        AuiRender {
+                            size,
+                            opacity: render.opacity,
+                            ..Default::default()
+                        },
+                        shadow_render,
+                        ShadowQuad,
+                        // shadows reach past their node, which they shouldn't make bigger
+                        PassThrough,
+                    ))
+                    .current_entity()
+                    .unwrap();
+                commands.push_children(entity, &[quad]);
+            }
+        }
+    }
+}
+
+// Between the shadow and the node, so the blurred scene covers the shadow inside the node
+pub const BACKDROP_Z: f32 = -0.00025;
+
+// Shows the scene behind the node blurred over `radius` pixels, under the node's own material,
+// which should be translucent for it to show through. Only the scene the main pass rendered is
+// blurred, not nodes drawn before this one, and only for nodes drawn into the primary window.
+// Needs UiRenderGraphBuilder::add_ui_backdrop.
+#[derive(Clone, Debug, Default)]
+pub struct BackdropBlur {
+    pub radius: f32,
+    pub corner_radius: f32,
+}
+
+// The child entity a BackdropBlur is drawn with
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BackdropQuad;
+
+// Draws the scene back into the window, behind every node
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BackdropCopy;
+
+#[derive(Default, RenderResources, Reflect, Clone, Debug)]
+pub struct BackdropRender {
+    // half size of the node, corner radius and blur radius in physical pixels
+    pub rect: Vec4,
+}
+
+// Material with the texture the main pass renders into, set by add_ui_backdrop
+#[derive(Clone, Debug, Default)]
+pub struct UiBackdrop {
+    pub(crate) material: Option<Handle<ColorMaterial>>,
+}
+
+#[derive(Default)]
+pub struct BackdropState {
+    copy: Option<Entity>,
+    window_size: Vec2,
+    rebind: bool,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn backdrop_system(
+    commands: &mut Commands,
+    mut state: Local<BackdropState>,
+    backdrop: Res<UiBackdrop>,
+    windows: Res<Windows>,
+    targets: Res<UiTargets>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    blurs: Query<(Entity, &BackdropBlur, &AuiRender, Option<&Children>), Without<BackdropQuad>>,
+    mut quads: Query<(&mut AuiRender, &mut BackdropRender), With<BackdropQuad>>,
+    quad_parents: Query<(Entity, &Parent), With<BackdropQuad>>,
+    mut copies: Query<&mut AuiRender, With<BackdropCopy>>,
+) {
+    let (material, window) = match (&backdrop.material, windows.get_primary()) {
+        (Some(material), Some(window)) => (material, window),
+        _ => return,
+    };
+
+    // the texture is made again after a resize, which the material only picks up once modified
+    if state.rebind {
+        materials.get_mut(material);
+    }
+    let window_size = Vec2::new(window.width(), window.height());
+    state.rebind = state.window_size != window_size;
+    state.window_size = window_size;
+
+    match state.copy.and_then(|copy| copies.get_mut(copy).ok()) {
+        Some(mut render) => {
+            if render.size != window_size {
+                render.size = window_size;
+            }
+        }
+        None => {
+            let copy = commands
+                .spawn((
+                    QUAD_HANDLE.typed::<Mesh>(),
+                    Draw::default(),
+                    material.clone(),
+                    Visible {
+                        is_transparent: true,
+                        ..Default::default()
+                    },
+                    RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                        UI_PIPELINE_HANDLE.typed(),
+                    )]),
+                    // nodes start at one DRAW_ORDER_STEP, so this is drawn before all of them
+                    Transform::default(),
+                    GlobalTransform::default(),
+                    AuiRender {
+                        size: window_size,
+                        ..Default::default()
+                    },
+                    BackdropCopy,
+                    PassThrough,
+                ))
+                .current_entity()
+                .unwrap();
+            state.copy = Some(copy);
+        }
+    }
+
+    // quads of nodes that lost their BackdropBlur
+    for (quad, parent) in quad_parents.iter() {
+        if blurs.get_component::<BackdropBlur>(parent.0).is_err() {
+            commands.despawn_recursive(quad);
+        }
+    }
+
+    for (entity, blur, render, children) in blurs.iter() {
+        // the main pass only renders the primary window's scene
+        if targets.owners.contains_key(&entity) {
+            continue;
+        }
+        let half = render.size / 2.;
+        let rect = Vec4::new(
+            half.x,
+            half.y,
+            blur.corner_radius,
+            blur.radius * window.scale_factor() as f32,
+        );
+        let quad = children
+            .iter()
+            .flat_map(|children| children.iter())
+            .find(|child| quads.get_component::<BackdropQuad>(**child).is_ok());
+        match quad {
+            Some(quad) => {
+                let (mut quad_render, mut backdrop_render) = quads.get_mut(*quad).unwrap();
+                if quad_render.size != render.size {
+                    quad_render.size = render.size;
+                }
+                if backdrop_render.rect != rect {
+                    backdrop_render.rect = rect;
+                }
+            }
+            None => {
+                let quad = commands
+                    .spawn((
+                        QUAD_HANDLE.typed::<Mesh>(),
+                        Draw::default(),
+                        material.clone(),
+                        Visible {
+                            is_transparent: true,
+                            ..Default::default()
+                        },
+                        RenderPipelines::from_pipelines(vec![RenderPipeline::new(
+                            BACKDROP_PIPELINE_HANDLE.typed(),
+                        )]),
+                        Transform::from_translation(Vec2::zero().extend(BACKDROP_Z)),
+                        GlobalTransform::default(),
+                        // opacity_system keeps this up to date from the next frame on
+                        AuiRender {
+                            size: render.size,
+                            opacity: render.opacity,
+                            ..Default::default()
+                        },
+                        BackdropRender { rect },
+                        BackdropQuad,
+                        PassThrough,
+                    ))
+                    .current_entity()
+                    .unwrap();
+                commands.push_children(entity, &[quad]);
+            }
+        }
+    }
+}
@@ -1,6 +1,10 @@
+use std::collections::HashMap;
+
+use bevy_ecs::Entity;
+use bevy_math::{Mat4, Vec2, Vec4};
 use bevy_reflect::Reflect;
-use bevy_render::renderer::RenderResources;
-use bevy_math::Vec2;
+use bevy_render::{color::Color, renderer::RenderResources};
+use bevy_window::{WindowId, Windows};
 
 #[derive(Clone, Debug, Default)]
 pub struct ANode {
@@ -8,11 +12,166 @@ pub struct ANode {
     pub constraint: Constraint,
     pub children_spread: Option<SpreadConstraint>,
     pub child_constraint: Option<ChildConstraint>,
+    // Takes the node out of its parent's spread, laying it out with its own anchors and constraint
+    pub absolute: bool,
+    // Overrides the LayoutDirection resource for this node and its descendants
+    pub layout_direction: Option<LayoutDirection>,
+    // Lays the node out against the whole window or texture instead of its parent, at any depth.
+    // Inside a spreading parent this should be combined with `absolute`.
+    pub ignore_safe_area: bool,
+    // Draws the node and its descendants after the rest of its root's tree, in front of it, for
+    // popups and menus
+    pub overlay: bool,
+}
+
+// Insets from the window edges that roots are kept out of, for notches, rounded corners and
+// overscan. Used as a resource for every root, or as a component to override it for one root.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SafeArea {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+impl SafeArea {
+    // Returns the space left inside the insets and the offset of its center from the window center
+    pub fn shrink(&self, size: Vec2) -> (Vec2, Vec2) {
+        let space = Vec2::new(
+            (size.x - self.left - self.right).max(0.),
+            (size.y - self.bottom - self.top).max(0.),
+        );
+        let origin = Vec2::new(self.left - self.right, self.bottom - self.top) / 2.;
+        (space, origin)
+    }
+}
+
+// Used both as a resource for the whole UI and per subtree through ANode::layout_direction.
+// RightToLeft mirrors horizontal anchors, x axis constraints and horizontal spreads.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LayoutDirection {
+    LeftToRight,
+    RightToLeft,
+}
+
+impl Default for LayoutDirection {
+    fn default() -> Self {
+        LayoutDirection::LeftToRight
+    }
+}
+
+// Makes a root render into the texture of the target added for `camera` through
+// UiRenderGraphBuilder::add_ui_texture_target, laid out at the size of that texture
+#[derive(Clone, Debug)]
+pub struct UiTexture {
+    pub camera: String,
+}
+
+// Makes a root render into a window other than the primary one, through the target added for
+// `camera` with UiRenderGraphBuilder::add_ui_window_target
+#[derive(Clone, Debug)]
+pub struct UiWindow {
+    pub camera: String,
+    pub window: WindowId,
+}
+
+// Top level entities drawn on behalf of a root, like batches and drag ghosts, which only the
+// camera of that root's target should see
+#[derive(Clone, Copy, Debug)]
+pub struct TargetRoot(pub Entity);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TargetSurface {
+    Texture(Vec2),
+    Window(WindowId),
+}
+
+// Every target's roots are laid out around the origin like the primary window's are, each
+// camera is kept to the nodes of its own target by target_visibility_system
+#[derive(Clone, Debug, Default)]
+pub struct UiTargets {
+    // Camera of each target and what it renders to
+    pub(crate) targets: Vec<(String, TargetSurface)>,
+    // Index of the target every entity of a targeted root's tree is drawn to, entities missing
+    // here are drawn by the primary ui camera
+    pub(crate) owners: HashMap<Entity, usize>,
+}
+
+impl UiTargets {
+    pub(crate) fn index(&self, camera: &str) -> Option<usize> {
+        self.targets.iter().position(|(target, _)| target == camera)
+    }
+
+    pub fn texture_size(&self, camera: &str) -> Option<Vec2> {
+        match self.targets.get(self.index(camera)?)?.1 {
+            TargetSurface::Texture(size) => Some(size),
+            TargetSurface::Window(_) => None,
+        }
+    }
+
+    pub(crate) fn window_index(&self, window: WindowId) -> Option<usize> {
+        self.targets
+            .iter()
+            .position(|(_, surface)| *surface == TargetSurface::Window(window))
+    }
+
+    pub fn has_window(&self, window: WindowId) -> bool {
+        self.window_index(window).is_some()
+    }
+
+    // Whether the entity is drawn into the window, which is how the cursor over that window is
+    // kept from reaching nodes of other windows laid out at the same coordinates
+    pub fn is_in_window(&self, entity: Entity, window: WindowId) -> bool {
+        self.owners.get(&entity).copied() == self.window_index(window)
+    }
+
+    // Size of the window or texture the entity is drawn into
+    pub fn target_size(&self, entity: Entity, windows: &Windows) -> Option<Vec2> {
+        let window = match self.owners.get(&entity) {
+            Some(index) => match self.targets.get(*index)?.1 {
+                TargetSurface::Texture(size) => return Some(size),
+                TargetSurface::Window(window) => windows.get(window),
+            },
+            None => windows.get_primary(),
+        }?;
+        Some(Vec2::new(window.width(), window.height()))
+    }
+
+    pub fn same_target(&self, a: Entity, b: Entity) -> bool {
+        self.owners.get(&a) == self.owners.get(&b)
+    }
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct ANodeLayoutCache {
-    pub(crate) sizes: Option<Vec<Vec2>>,
+    // (child, space, origin) for every child placed by the spread
+    pub(crate) sizes: Option<Vec<(Entity, Vec2, Vec2)>>,
+}
+
+// Hidden nodes keep their space in layout, collapsed nodes are skipped by the solver entirely.
+// Both hide the node and all of its descendants.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DisplayMode {
+    Shown,
+    Hidden,
+    Collapsed,
+}
+
+impl Default for DisplayMode {
+    fn default() -> Self {
+        DisplayMode::Shown
+    }
+}
+
+// Multiplies into the alpha of the node and all of its descendants, nested opacities multiply
+// together
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Opacity(pub f32);
+
+impl Default for Opacity {
+    fn default() -> Self {
+        Opacity(1.)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -32,6 +191,22 @@ pub enum Constraint {
     MaxAspect(f32),
 }
 
+impl Constraint {
+    pub fn mirrored(&self) -> Self {
+        match self {
+            Constraint::Independent { x, y } => Constraint::Independent {
+                x: x.mirrored(),
+                y: *y,
+            },
+            Constraint::SetYWithX { x, aspect } => Constraint::SetYWithX {
+                x: x.mirrored(),
+                aspect: *aspect,
+            },
+            other => other.clone(),
+        }
+    }
+}
+
 impl Default for Constraint {
     fn default() -> Self {
         Constraint::Independent {
@@ -41,9 +216,192 @@ impl Default for Constraint {
     }
 }
 
-#[derive(Default, RenderResources, Reflect, Clone, Debug)]
+#[derive(RenderResources, Reflect, Clone, Debug)]
 pub struct AuiRender {
     pub size: Vec2,
+    // min and max corners of the rect in UI space the node is clipped to, set for the contents of
+    // a VirtualList
+    pub clip: Vec4,
+    // amount, mode (FillMode + 1, 0 for none), reversed and an unused slot, written from Fill
+    pub fill: Vec4,
+    // shape (GradientShape + 1, 0 for none), stop count, space and an unused slot
+    pub gradient: Vec4,
+    // angle for linear gradients, or center x, center y and radius for radial ones
+    pub gradient_shape: Vec4,
+    pub gradient_stops: Vec4,
+    // one stop color per column
+    pub gradient_colors: Mat4,
+    // offset and scale of the texture coordinates, for showing part of a texture
+    pub uv_rect: Vec4,
+    // offset and scale of the node's coordinates in the image, which is clipped outside of 0..1
+    // unless it's tiled
+    pub image_rect: Vec4,
+    pub image_tiled: f32,
+    // product of the Opacity of the node and its ancestors
+    pub opacity: f32,
+}
+
+impl Default for AuiRender {
+    fn default() -> Self {
+        Self {
+            size: Vec2::zero(),
+            clip: Vec4::new(f32::MIN, f32::MIN, f32::MAX, f32::MAX),
+            fill: Vec4::zero(),
+            gradient: Vec4::zero(),
+            gradient_shape: Vec4::zero(),
+            gradient_stops: Vec4::zero(),
+            gradient_colors: Mat4::zero(),
+            uv_rect: Vec4::new(0., 0., 1., 1.),
+            image_rect: Vec4::new(0., 0., 1., 1.),
+            image_tiled: 0.,
+            opacity: 1.,
+        }
+    }
+}
+
+// Cuts the node off in the shader, without touching layout
+#[derive(Clone, Copy, Debug)]
+pub struct Fill {
+    pub amount: f32,
+    pub mode: FillMode,
+    pub reversed: bool,
+}
+
+impl Default for Fill {
+    fn default() -> Self {
+        Self {
+            amount: 1.,
+            mode: Default::default(),
+            reversed: false,
+        }
+    }
+}
+
+impl Fill {
+    pub(crate) fn as_vec4(&self) -> Vec4 {
+        let mode = match self.mode {
+            FillMode::Horizontal => 1.,
+            FillMode::Vertical => 2.,
+            FillMode::Radial => 3.,
+            FillMode::Clock => 4.,
+        };
+        let reversed = if self.reversed { 1. } else { 0. };
+        Vec4::new(self.amount.clamp(0., 1.), mode, reversed, 0.)
+    }
+}
+
+// Horizontal fills from the left, vertical from the bottom, radial from the center outwards and
+// clock clockwise from the top. Reversing flips where the fill starts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FillMode {
+    Horizontal,
+    Vertical,
+    Radial,
+    Clock,
+}
+
+impl Default for FillMode {
+    fn default() -> Self {
+        FillMode::Horizontal
+    }
+}
+
+// Multiplies the node's color with a gradient in the shader. Up to four stops are used, with
+// positions from 0 to 1 in increasing order.
+#[derive(Clone, Debug)]
+pub struct Gradient {
+    pub shape: GradientShape,
+    pub stops: Vec<(f32, Color)>,
+    pub space: GradientSpace,
+}
+
+impl Gradient {
+    // `angle` is in radians, counter-clockwise from a left to right gradient
+    pub fn linear(angle: f32, stops: Vec<(f32, Color)>) -> Self {
+        Self {
+            shape: GradientShape::Linear { angle },
+            stops,
+            space: Default::default(),
+        }
+    }
+
+    pub fn radial(center: Vec2, radius: f32, stops: Vec<(f32, Color)>) -> Self {
+        Self {
+            shape: GradientShape::Radial { center, radius },
+            stops,
+            space: Default::default(),
+        }
+    }
+
+    // Blends a color per corner across the node
+    pub fn corners(
+        bottom_left: Color,
+        bottom_right: Color,
+        top_left: Color,
+        top_right: Color,
+    ) -> Self {
+        Self {
+            shape: GradientShape::Corners,
+            stops: vec![
+                (0., bottom_left),
+                (0., bottom_right),
+                (0., top_left),
+                (0., top_right),
+            ],
+            space: Default::default(),
+        }
+    }
+
+    pub(crate) fn as_uniforms(&self) -> (Vec4, Vec4, Vec4, Mat4) {
+        let (shape, params) = match self.shape {
+            GradientShape::Linear { angle } => (1., Vec4::new(angle, 0., 0., 0.)),
+            GradientShape::Radial { center, radius } => {
+                (2., Vec4::new(center.x, center.y, radius, 0.))
+            }
+            GradientShape::Corners => (3., Vec4::zero()),
+        };
+        let space = match self.space {
+            GradientSpace::Linear => 0.,
+            GradientSpace::Srgb => 1.,
+        };
+        let count = self.stops.len().min(4);
+        // without stops there's nothing to blend, so the node is drawn as if it had no gradient
+        let shape = if count == 0 { 0. } else { shape };
+        let mut stops = [1.; 4];
+        let mut colors = [Vec4::zero(); 4];
+        for (i, (position, color)) in self.stops.iter().take(4).enumerate() {
+            stops[i] = *position;
+            colors[i] = Vec4::from(*color);
+        }
+        (
+            Vec4::new(shape, count as f32, space, 0.),
+            params,
+            Vec4::from(stops),
+            Mat4::from_cols(colors[0], colors[1], colors[2], colors[3]),
+        )
+    }
+}
+
+// Radial gradients are centered on a point in 0 to 1 node coordinates, from the bottom left, and
+// reach their last stop `radius` units away from it
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GradientShape {
+    Linear { angle: f32 },
+    Radial { center: Vec2, radius: f32 },
+    Corners,
+}
+
+// The color space stops are blended in
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GradientSpace {
+    Linear,
+    Srgb,
+}
+
+impl Default for GradientSpace {
+    fn default() -> Self {
+        GradientSpace::Srgb
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -51,18 +409,43 @@ pub struct ChildConstraint {
     pub weight: f32,
     pub min_size: f32,
     pub max_size: f32,
+    pub length: ChildLength,
+}
+
+impl ChildConstraint {
+    // Used for spread children that don't have a ChildConstraint
+    pub const DEFAULT: ChildConstraint = ChildConstraint {
+        weight: 1.,
+        min_size: 0.,
+        max_size: f32::MAX,
+        length: ChildLength::Weighted,
+    };
 }
 
 impl Default for ChildConstraint {
     fn default() -> Self {
-        Self {
-            weight: 1.,
-            min_size: 0.,
-            max_size: f32::MAX,
-        }
+        Self::DEFAULT
+    }
+}
+
+// Fixed and intrinsic lengths are taken out of the free length before weights are distributed
+#[derive(Clone, Copy, Debug)]
+pub enum ChildLength {
+    Weighted,
+    Fixed(f32),
+    Intrinsic,
+}
+
+impl Default for ChildLength {
+    fn default() -> Self {
+        ChildLength::Weighted
     }
 }
 
+// Size of a node's content (text, images...), read by ChildLength::Intrinsic
+#[derive(Clone, Copy, Debug, Default)]
+pub struct IntrinsicSize(pub Vec2);
+
 #[derive(Clone, Debug, Default)]
 pub struct SpreadConstraint {
     pub margin: f32,
@@ -78,6 +461,17 @@ pub enum Direction {
     Right,
 }
 
+impl Direction {
+    pub fn mirrored(&self) -> Self {
+        match self {
+            Direction::Left => Direction::Right,
+            Direction::Right => Direction::Left,
+            Direction::Up => Direction::Up,
+            Direction::Down => Direction::Down,
+        }
+    }
+}
+
 impl Default for Direction {
     fn default() -> Self {
         Direction::Right
@@ -92,6 +486,18 @@ pub enum AxisConstraint {
     Centered(f32),
 }
 
+impl AxisConstraint {
+    // Swaps which side of the axis the constraint is measured from
+    pub fn mirrored(self) -> Self {
+        match self {
+            AxisConstraint::DoublePadding(p1, p2) => AxisConstraint::DoublePadding(p2, p1),
+            AxisConstraint::PaddingAndSize(p, s) => AxisConstraint::InversePaddingAndSize(p, s),
+            AxisConstraint::InversePaddingAndSize(p, s) => AxisConstraint::PaddingAndSize(p, s),
+            AxisConstraint::Centered(s) => AxisConstraint::Centered(s),
+        }
+    }
+}
+
 impl Default for AxisConstraint {
     fn default() -> Self {
         AxisConstraint::DoublePadding(0., 0.)
@@ -141,6 +547,10 @@ impl Anchors {
     pub const fn y(&self) -> (f32, f32) {
         (self.bottom, self.top)
     }
+
+    pub fn mirrored_x(&self) -> Self {
+        Anchors::new(1. - self.right, 1. - self.left, self.bottom, self.top)
+    }
 }
 
 impl Default for Anchors {
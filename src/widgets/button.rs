@@ -0,0 +1,113 @@
+use bevy_asset::Handle;
+use bevy_ecs::{Bundle, Query, Res};
+use bevy_render::{
+    mesh::Mesh,
+    prelude::{Draw, RenderPipelines, Visible},
+};
+use bevy_sprite::ColorMaterial;
+use bevy_transform::components::{GlobalTransform, Transform};
+
+use crate::{
+    ANode, ANodeLayoutCache, AUINode, AtlasMaterials, AuiRender, Disabled, DisplayMode, Interaction,
+};
+
+#[derive(Clone, Debug, Default)]
+pub struct ButtonMaterials {
+    pub normal: Handle<ColorMaterial>,
+    pub hovered: Handle<ColorMaterial>,
+    pub pressed: Handle<ColorMaterial>,
+    pub disabled: Handle<ColorMaterial>,
+}
+
+impl ButtonMaterials {
+    pub fn get(&self, interaction: Interaction, disabled: bool) -> &Handle<ColorMaterial> {
+        if disabled {
+            return &self.disabled;
+        }
+        match interaction {
+            Interaction::None => &self.normal,
+            Interaction::Hovered => &self.hovered,
+            Interaction::Pressed => &self.pressed,
+        }
+    }
+}
+
+// Clicks are reported through the Clicked event, disable a button by inserting Disabled
+#[derive(Bundle, Clone, Debug)]
+pub struct ButtonBundle {
+    pub mesh: Handle<Mesh>,
+    pub draw: Draw,
+    pub material: Handle<ColorMaterial>,
+    pub visible: Visible,
+    pub render_pipelines: RenderPipelines,
+    pub transform: Transform,
+    pub global_transform: GlobalTransform,
+    pub node: ANode,
+    pub display: DisplayMode,
+    pub layout_cache: ANodeLayoutCache,
+    pub render_data: AuiRender,
+    pub interaction: Interaction,
+    pub materials: ButtonMaterials,
+}
+
+impl ButtonBundle {
+    pub fn new(node: ANode, materials: ButtonMaterials) -> Self {
+        Self {
+            material: materials.normal.clone(),
+            node,
+            materials,
+            ..Default::default()
+        }
+    }
+}
+
+impl Default for ButtonBundle {
+    fn default() -> Self {
+        let AUINode {
+            mesh,
+            draw,
+            material,
+            visible,
+            render_pipelines,
+            transform,
+            global_transform,
+            node,
+            display,
+            layout_cache,
+            render_data,
+        } = Default::default();
+        Self {
+            mesh,
+            draw,
+            material,
+            visible,
+            render_pipelines,
+            transform,
+            global_transform,
+            node,
+            display,
+            layout_cache,
+            render_data,
+            interaction: Default::default(),
+            materials: Default::default(),
+        }
+    }
+}
+
+pub fn button_system(
+    atlas_materials: Res<AtlasMaterials>,
+    mut buttons: Query<(
+        &Interaction,
+        &ButtonMaterials,
+        Option<&Disabled>,
+        &mut Handle<ColorMaterial>,
+    )>,
+) {
+    for (interaction, materials, disabled, mut material) in buttons.iter_mut() {
+        let target = materials.get(*interaction, disabled.is_some());
+        // atlas images draw with a copy of the button's material
+        if atlas_materials.base(&material) != target {
+            *material = target.clone();
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use bevy_app::{EventReader, Events};
+use bevy_ecs::{Changed, Commands, Entity, Local, Query, Res, ResMut, With};
+use bevy_transform::{components::Children, hierarchy::BuildChildren};
+
+use crate::{
+    widgets::slider::{place_along, SliderAxis},
+    ANode, AUINode, Clicked, DisplayMode, Interaction, PassThrough, ValueChanged,
+};
+
+// The check mark is a child marked with CheckboxMark, which is hidden while unchecked
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Checkbox {
+    pub checked: bool,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CheckboxMark;
+
+// The knob is a child marked with ToggleKnob, which sits at the start of the track while off
+// and at the end while on
+#[derive(Clone, Copy, Debug)]
+pub struct Toggle {
+    pub on: bool,
+    pub knob_size: f32,
+}
+
+impl Default for Toggle {
+    fn default() -> Self {
+        Self {
+            on: false,
+            knob_size: 20.,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ToggleKnob;
+
+pub fn spawn_checkbox(
+    commands: &mut Commands,
+    frame: AUINode,
+    mark: AUINode,
+    checkbox: Checkbox,
+) -> Entity {
+    let entity = commands
+        .spawn(frame)
+        .with(checkbox)
+        .with(Interaction::default())
+        .current_entity()
+        .unwrap();
+    commands.with_children(|parent| {
+        parent.spawn(mark).with(CheckboxMark).with(PassThrough);
+    });
+    entity
+}
+
+pub fn spawn_toggle(
+    commands: &mut Commands,
+    track: AUINode,
+    knob: AUINode,
+    toggle: Toggle,
+) -> Entity {
+    let entity = commands
+        .spawn(track)
+        .with(toggle)
+        .with(Interaction::default())
+        .current_entity()
+        .unwrap();
+    commands.with_children(|parent| {
+        parent.spawn(knob).with(ToggleKnob);
+    });
+    entity
+}
+
+pub fn checkbox_system(
+    mut reader: Local<EventReader<Clicked>>,
+    clicks: Res<Events<Clicked>>,
+    mut events: ResMut<Events<ValueChanged<bool>>>,
+    mut checkboxes: Query<&mut Checkbox>,
+    mut toggles: Query<&mut Toggle>,
+) {
+    for click in reader.iter(&clicks) {
+        let value = if let Ok(mut checkbox) = checkboxes.get_mut(click.entity) {
+            checkbox.checked = !checkbox.checked;
+            checkbox.checked
+        } else if let Ok(mut toggle) = toggles.get_mut(click.entity) {
+            toggle.on = !toggle.on;
+            toggle.on
+        } else {
+            continue;
+        };
+        events.send(ValueChanged {
+            entity: click.entity,
+            value,
+        });
+    }
+}
+
+pub fn checkbox_mark_system(
+    checkboxes: Query<(&Checkbox, &Children), Changed<Checkbox>>,
+    mut marks: Query<&mut DisplayMode, With<CheckboxMark>>,
+) {
+    for (checkbox, children) in checkboxes.iter() {
+        let mode = if checkbox.checked {
+            DisplayMode::Shown
+        } else {
+            DisplayMode::Hidden
+        };
+        for child in children.iter() {
+            if let Ok(mut mark) = marks.get_mut(*child) {
+                *mark = mode;
+            }
+        }
+    }
+}
+
+pub fn toggle_knob_system(
+    toggles: Query<(&Toggle, &Children), Changed<Toggle>>,
+    mut knobs: Query<&mut ANode, With<ToggleKnob>>,
+) {
+    for (toggle, children) in toggles.iter() {
+        let fraction = if toggle.on { 1. } else { 0. };
+        for child in children.iter() {
+            if let Ok(mut knob) = knobs.get_mut(*child) {
+                place_along(
+                    &mut knob,
+                    SliderAxis::Horizontal,
+                    fraction,
+                    toggle.knob_size,
+                );
+            }
+        }
+    }
+}
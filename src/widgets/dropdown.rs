@@ -0,0 +1,268 @@
+use bevy_app::{EventReader, Events};
+use bevy_ecs::{Changed, Commands, Entity, Local, Query, Res, ResMut};
+use bevy_input::{
+    keyboard::KeyCode,
+    mouse::{MouseButton, MouseWheel},
+    Input,
+};
+use bevy_transform::{
+    components::{Children, GlobalTransform},
+    hierarchy::BuildChildren,
+};
+use bevy_window::Windows;
+
+use crate::{
+    ANode, AUINode, Anchors, AuiRender, AxisConstraint, Clicked, Constraint, DisplayMode,
+    Interaction, UiTargets, ValueChanged,
+};
+
+// A select box. Its options live in a DropdownList child that opens as an overlay below it,
+// showing as many options as fit between the box and the bottom of the window or texture it's
+// drawn into.
+#[derive(Clone, Debug)]
+pub struct Dropdown {
+    pub selected: Option<usize>,
+    pub open: bool,
+    // The option keyboard selection acts on
+    pub highlighted: usize,
+    pub option_height: f32,
+    pub max_visible: usize,
+    // Index of the first visible option
+    pub scroll: usize,
+    options: usize,
+    visible: usize,
+}
+
+impl Dropdown {
+    pub fn new(selected: Option<usize>, option_height: f32) -> Self {
+        Self {
+            selected,
+            open: false,
+            highlighted: selected.unwrap_or(0),
+            option_height,
+            max_visible: 8,
+            scroll: 0,
+            options: 0,
+            visible: 0,
+        }
+    }
+
+    fn scroll_to(&mut self, index: usize) {
+        if index < self.scroll {
+            self.scroll = index;
+        } else if self.visible > 0 && index >= self.scroll + self.visible {
+            self.scroll = index + 1 - self.visible;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct DropdownList;
+
+#[derive(Clone, Copy, Debug)]
+pub struct DropdownOption {
+    pub dropdown: Entity,
+    pub index: usize,
+}
+
+pub fn spawn_dropdown(
+    commands: &mut Commands,
+    frame: AUINode,
+    mut list: AUINode,
+    options: Vec<AUINode>,
+    mut dropdown: Dropdown,
+) -> Entity {
+    dropdown.options = options.len();
+    list.node.absolute = true;
+    list.node.overlay = true;
+    list.display = DisplayMode::Collapsed;
+
+    let entity = commands
+        .spawn(frame)
+        .with(dropdown)
+        .with(Interaction::default())
+        .current_entity()
+        .unwrap();
+    let list = commands
+        .spawn(list)
+        .with(DropdownList)
+        .current_entity()
+        .unwrap();
+    commands.push_children(entity, &[list]);
+    for (index, option) in options.into_iter().enumerate() {
+        let option = commands
+            .spawn(option)
+            .with(DropdownOption {
+                dropdown: entity,
+                index,
+            })
+            .with(Interaction::default())
+            .current_entity()
+            .unwrap();
+        commands.push_children(list, &[option]);
+    }
+    entity
+}
+
+#[derive(Default)]
+pub struct DropdownState {
+    clicks: EventReader<Clicked>,
+    wheel: EventReader<MouseWheel>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn dropdown_system(
+    mut state: Local<DropdownState>,
+    clicks: Res<Events<Clicked>>,
+    wheel: Res<Events<MouseWheel>>,
+    mouse: Res<Input<MouseButton>>,
+    keys: Res<Input<KeyCode>>,
+    windows: Res<Windows>,
+    targets: Res<UiTargets>,
+    mut events: ResMut<Events<ValueChanged<usize>>>,
+    mut dropdowns: Query<(
+        Entity,
+        &mut Dropdown,
+        &Interaction,
+        &GlobalTransform,
+        &AuiRender,
+    )>,
+    options: Query<(&DropdownOption, &Interaction)>,
+) {
+    let mut select = |entity, dropdown: &mut Dropdown, index| {
+        dropdown.open = false;
+        dropdown.highlighted = index;
+        if dropdown.selected != Some(index) {
+            dropdown.selected = Some(index);
+            events.send(ValueChanged {
+                entity,
+                value: index,
+            });
+        }
+    };
+
+    for click in state.clicks.iter(&clicks) {
+        if let Ok((_, mut dropdown, ..)) = dropdowns.get_mut(click.entity) {
+            dropdown.open = !dropdown.open;
+            let highlighted = dropdown.selected.unwrap_or(0);
+            dropdown.highlighted = highlighted;
+        } else if let Ok((option, _)) = options.get(click.entity) {
+            if let Ok((entity, mut dropdown, ..)) = dropdowns.get_mut(option.dropdown) {
+                select(entity, &mut *dropdown, option.index);
+            }
+        }
+    }
+    let scrolled = state.wheel.iter(&wheel).map(|event| event.y).sum::<f32>();
+
+    for (entity, mut dropdown, interaction, transform, render) in dropdowns.iter_mut() {
+        if !dropdown.open {
+            continue;
+        }
+
+        let on_options = |state| {
+            options
+                .iter()
+                .any(|(option, interaction)| option.dropdown == entity && *interaction == state)
+        };
+
+        // pressing anywhere outside of the box and its options closes it
+        if mouse.just_pressed(MouseButton::Left)
+            && *interaction != Interaction::Pressed
+            && !on_options(Interaction::Pressed)
+        {
+            dropdown.open = false;
+            continue;
+        }
+        if keys.just_pressed(KeyCode::Escape) {
+            dropdown.open = false;
+            continue;
+        }
+
+        let target_height = targets
+            .target_size(entity, &windows)
+            .map_or(0., |size| size.y);
+        let below = transform.translation.y - render.size.y / 2. + target_height / 2.;
+        let fits = (below / dropdown.option_height).floor().max(1.) as usize;
+        let visible = dropdown.options.min(dropdown.max_visible).min(fits);
+        if dropdown.visible != visible {
+            dropdown.visible = visible;
+        }
+
+        let last = dropdown.options.saturating_sub(1);
+        if keys.just_pressed(KeyCode::Down) && dropdown.highlighted < last {
+            dropdown.highlighted += 1;
+        }
+        if keys.just_pressed(KeyCode::Up) && dropdown.highlighted > 0 {
+            dropdown.highlighted -= 1;
+        }
+        if keys.just_pressed(KeyCode::Down) || keys.just_pressed(KeyCode::Up) {
+            let highlighted = dropdown.highlighted;
+            dropdown.scroll_to(highlighted);
+        }
+        if scrolled != 0. && on_options(Interaction::Hovered) {
+            let max_scroll = dropdown.options.saturating_sub(dropdown.visible) as f32;
+            dropdown.scroll = (dropdown.scroll as f32 - scrolled).clamp(0., max_scroll) as usize;
+        }
+        if keys.just_pressed(KeyCode::Return) && dropdown.options > 0 {
+            let highlighted = dropdown.highlighted;
+            select(entity, &mut *dropdown, highlighted);
+        }
+    }
+}
+
+// Opens and closes the list and lays out the options that are scrolled into view
+pub fn dropdown_list_system(
+    dropdowns: Query<(&Dropdown, &Children), Changed<Dropdown>>,
+    lists: Query<&Children>,
+    mut nodes: Query<(
+        &mut ANode,
+        &mut DisplayMode,
+        Option<&DropdownList>,
+        Option<&DropdownOption>,
+    )>,
+) {
+    for (dropdown, children) in dropdowns.iter() {
+        for child in children.iter() {
+            if nodes.get_component::<DropdownList>(*child).is_err() {
+                continue;
+            }
+            let (mut list, mut display, ..) = nodes.get_mut(*child).unwrap();
+            if !dropdown.open {
+                *display = DisplayMode::Collapsed;
+                continue;
+            }
+            *display = DisplayMode::Shown;
+            let height = dropdown.visible as f32 * dropdown.option_height;
+            list.anchors = Anchors::new(0., 1., 0., 0.);
+            list.constraint = Constraint::Independent {
+                x: AxisConstraint::DoublePadding(0., 0.),
+                y: AxisConstraint::PaddingAndSize(-height, height),
+            };
+
+            for option in lists.get(*child).iter().flat_map(|options| options.iter()) {
+                let (mut node, mut display, _, option) = match nodes.get_mut(*option) {
+                    Ok(option) => option,
+                    Err(_) => continue,
+                };
+                let index = match option {
+                    Some(option) => option.index,
+                    None => continue,
+                };
+                if index < dropdown.scroll || index >= dropdown.scroll + dropdown.visible {
+                    *display = DisplayMode::Collapsed;
+                    continue;
+                }
+                *display = DisplayMode::Shown;
+                node.absolute = true;
+                node.anchors = Anchors::TOP_FULL;
+                node.constraint = Constraint::Independent {
+                    x: AxisConstraint::DoublePadding(0., 0.),
+                    y: AxisConstraint::InversePaddingAndSize(
+                        (index - dropdown.scroll) as f32 * dropdown.option_height,
+                        dropdown.option_height,
+                    ),
+                };
+            }
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use bevy_asset::{Assets, Handle};
+use bevy_core::Time;
+use bevy_ecs::{Commands, Entity, Query, Res, ResMut, With};
+use bevy_math::{Vec2, Vec4};
+use bevy_render::texture::Texture;
+use bevy_sprite::{ColorMaterial, TextureAtlas};
+
+use crate::{AuiRender, IntrinsicSize};
+
+// Shows one region of a texture atlas on the node. The node's material is swapped for a copy of
+// it showing the atlas texture, shared by every image of that atlas and material, which keeps
+// following the color of the material it was copied from. Removing the AtlasImage puts the
+// original material back.
+#[derive(Clone, Debug, Default)]
+pub struct AtlasImage {
+    pub atlas: Handle<TextureAtlas>,
+    pub index: usize,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+// Steps an AtlasImage through `frames`, spending `frame_time` seconds on each
+#[derive(Clone, Debug)]
+pub struct AtlasAnimation {
+    pub frames: Vec<usize>,
+    pub frame_time: f32,
+    pub looping: bool,
+    pub paused: bool,
+    elapsed: f32,
+}
+
+impl AtlasAnimation {
+    pub fn new(frames: Vec<usize>, frame_time: f32) -> Self {
+        Self {
+            frames,
+            frame_time,
+            looping: true,
+            paused: false,
+            elapsed: 0.,
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.elapsed = 0.;
+    }
+}
+
+pub fn atlas_animation_system(
+    time: Res<Time>,
+    mut images: Query<(&mut AtlasImage, &mut AtlasAnimation)>,
+) {
+    for (mut image, mut animation) in images.iter_mut() {
+        if animation.paused || animation.frames.is_empty() {
+            continue;
+        }
+        animation.elapsed += time.delta_seconds();
+        let step = if animation.frame_time > 0. {
+            (animation.elapsed / animation.frame_time) as usize
+        } else {
+            0
+        };
+        let len = animation.frames.len();
+        let step = if animation.looping {
+            step % len
+        } else {
+            step.min(len - 1)
+        };
+        let index = animation.frames[step];
+        if image.index != index {
+            image.index = index;
+        }
+    }
+}
+
+// The materials atlas images are drawn with, made from the materials the nodes were given
+#[derive(Default)]
+pub struct AtlasMaterials {
+    derived: HashMap<(Handle<TextureAtlas>, Handle<ColorMaterial>), Handle<ColorMaterial>>,
+    bases: HashMap<Handle<ColorMaterial>, Handle<ColorMaterial>>,
+}
+
+impl AtlasMaterials {
+    // The material a node was given, for systems that switch materials themselves
+    pub fn base<'a>(&'a self, material: &'a Handle<ColorMaterial>) -> &'a Handle<ColorMaterial> {
+        self.bases.get(material).unwrap_or(material)
+    }
+}
+
+// Atlases may still be loading, so images are checked every frame until they can be shown
+pub fn atlas_image_system(
+    mut atlas_materials: ResMut<AtlasMaterials>,
+    atlases: Res<Assets<TextureAtlas>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    images: Query<(Entity, &AtlasImage)>,
+    mut nodes: Query<(&mut AuiRender, &mut Handle<ColorMaterial>)>,
+) {
+    for entity in images.removed::<AtlasImage>() {
+        if let Ok((mut render_data, mut material)) = nodes.get_mut(*entity) {
+            render_data.uv_rect = AuiRender::default().uv_rect;
+            let base = atlas_materials.base(&material).clone();
+            if *material != base {
+                *material = base;
+            }
+        }
+    }
+
+    for (entity, image) in images.iter() {
+        let (mut render_data, mut material) = match nodes.get_mut(entity) {
+            Ok(node) => node,
+            Err(_) => continue,
+        };
+        let atlas = match atlases.get(&image.atlas) {
+            Some(atlas) => atlas,
+            None => continue,
+        };
+        let rect = match atlas.textures.get(image.index) {
+            Some(rect) => rect,
+            None => continue,
+        };
+
+        let mut offset = rect.min / atlas.size;
+        let mut scale = (rect.max - rect.min) / atlas.size;
+        if image.flip_x {
+            offset.x += scale.x;
+            scale.x = -scale.x;
+        }
+        if image.flip_y {
+            offset.y += scale.y;
+            scale.y = -scale.y;
+        }
+        let uv_rect = Vec4::new(offset.x, offset.y, scale.x, scale.y);
+        if render_data.uv_rect != uv_rect {
+            render_data.uv_rect = uv_rect;
+        }
+
+        let base = atlas_materials.base(&material).clone();
+        let color = materials
+            .get(&base)
+            .map_or_else(Default::default, |base| base.color);
+        let AtlasMaterials { derived, bases } = &mut *atlas_materials;
+        let atlas_material = derived
+            .entry((image.atlas.clone(), base.clone()))
+            .or_insert_with(|| {
+                let atlas_material = materials.add(ColorMaterial::modulated_texture(
+                    atlas.texture.clone(),
+                    color,
+                ));
+                bases.insert(atlas_material.clone(), base);
+                atlas_material
+            });
+        // get_mut marks the material modified, so only when the tint changed
+        if materials
+            .get(&*atlas_material)
+            .map_or(false, |m| m.color != color)
+        {
+            materials.get_mut(&*atlas_material).unwrap().color = color;
+        }
+        if *material != *atlas_material {
+            *material = atlas_material.clone();
+        }
+    }
+}
+
+// How a node's texture is fit into its size. Contain and cover keep the texture's aspect ratio,
+// contain leaving the rest of the node empty and cover cutting off what doesn't fit. Tile repeats
+// the texture at its native size from the node's top left corner.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ImageScale {
+    Stretch,
+    Contain,
+    Cover,
+    Tile,
+}
+
+impl Default for ImageScale {
+    fn default() -> Self {
+        ImageScale::Stretch
+    }
+}
+
+// Keeps the node's IntrinsicSize at the native size of its texture, or of its atlas region
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NativeSize;
+
+fn image_size(
+    image: Option<&AtlasImage>,
+    material: Option<&Handle<ColorMaterial>>,
+    atlases: &Assets<TextureAtlas>,
+    materials: &Assets<ColorMaterial>,
+    textures: &Assets<Texture>,
+) -> Option<Vec2> {
+    if let Some(image) = image {
+        let rect = atlases.get(&image.atlas)?.textures.get(image.index)?;
+        return Some(rect.max - rect.min);
+    }
+    let texture = materials.get(material?)?.texture.as_ref()?;
+    let size = textures.get(texture)?.size;
+    Some(Vec2::new(size.width as f32, size.height as f32))
+}
+
+pub fn native_size_system(
+    commands: &mut Commands,
+    atlases: Res<Assets<TextureAtlas>>,
+    materials: Res<Assets<ColorMaterial>>,
+    textures: Res<Assets<Texture>>,
+    mut nodes: Query<
+        (
+            Entity,
+            Option<&AtlasImage>,
+            Option<&Handle<ColorMaterial>>,
+            Option<&mut IntrinsicSize>,
+        ),
+        With<NativeSize>,
+    >,
+) {
+    for (entity, image, material, intrinsic) in nodes.iter_mut() {
+        let size = match image_size(image, material, &atlases, &materials, &textures) {
+            Some(size) => size,
+            None => continue,
+        };
+        match intrinsic {
+            Some(mut intrinsic) => {
+                if intrinsic.0 != size {
+                    intrinsic.0 = size;
+                }
+            }
+            None => {
+                commands.insert_one(entity, IntrinsicSize(size));
+            }
+        }
+    }
+}
+
+// Runs after layout, contain and cover depend on the node's solved size
+pub fn image_scale_system(
+    atlases: Res<Assets<TextureAtlas>>,
+    materials: Res<Assets<ColorMaterial>>,
+    textures: Res<Assets<Texture>>,
+    nodes: Query<(
+        Entity,
+        &ImageScale,
+        Option<&AtlasImage>,
+        &Handle<ColorMaterial>,
+    )>,
+    mut renders: Query<&mut AuiRender>,
+) {
+    for entity in nodes.removed::<ImageScale>() {
+        if let Ok(mut render_data) = renders.get_mut(*entity) {
+            let default = AuiRender::default();
+            render_data.image_rect = default.image_rect;
+            render_data.image_tiled = default.image_tiled;
+        }
+    }
+
+    for (entity, scale, image, material) in nodes.iter() {
+        let mut render_data = match renders.get_mut(entity) {
+            Ok(render_data) => render_data,
+            Err(_) => continue,
+        };
+        let image_size = match image_size(image, Some(material), &atlases, &materials, &textures) {
+            Some(size) if size.x > 0. && size.y > 0. => size,
+            _ => continue,
+        };
+        let node_size = render_data.size;
+        // share of the image shown across the node on each axis
+        let shown = match scale {
+            ImageScale::Stretch => Vec2::one(),
+            ImageScale::Contain | ImageScale::Cover => {
+                let ratio = node_size / image_size;
+                let fit = if *scale == ImageScale::Contain {
+                    ratio.x.min(ratio.y)
+                } else {
+                    ratio.x.max(ratio.y)
+                };
+                if fit > 0. {
+                    ratio / fit
+                } else {
+                    Vec2::one()
+                }
+            }
+            ImageScale::Tile => node_size / image_size,
+        };
+        let tiled = *scale == ImageScale::Tile;
+        let offset = if tiled {
+            Vec2::zero()
+        } else {
+            (Vec2::one() - shown) / 2.
+        };
+        let image_rect = Vec4::new(offset.x, offset.y, shown.x, shown.y);
+        if render_data.image_rect != image_rect {
+            render_data.image_rect = image_rect;
+        }
+        let image_tiled = if tiled { 1. } else { 0. };
+        if render_data.image_tiled != image_tiled {
+            render_data.image_tiled = image_tiled;
+        }
+    }
+}
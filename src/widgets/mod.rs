@@ -0,0 +1,19 @@
+use bevy_ecs::Entity;
+
+pub mod button;
+pub mod checkbox;
+pub mod dropdown;
+pub mod image;
+pub mod slider;
+pub mod split;
+pub mod tabs;
+pub mod text_input;
+pub mod tooltip;
+pub mod virtual_list;
+
+// Sent by value widgets whenever their value is changed through user input
+#[derive(Clone, Debug)]
+pub struct ValueChanged<T> {
+    pub entity: Entity,
+    pub value: T,
+}
@@ -0,0 +1,176 @@
+use bevy_app::Events;
+use bevy_ecs::{Changed, Commands, Entity, Query, Res, ResMut, With};
+use bevy_transform::{
+    components::{Children, GlobalTransform, Parent},
+    hierarchy::BuildChildren,
+};
+
+use crate::{
+    layout::resolve_direction, ANode, AUINode, Anchors, AuiRender, AxisConstraint, Constraint,
+    Interaction, LayoutDirection, PassThrough, UiCursor, ValueChanged,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SliderAxis {
+    Horizontal,
+    Vertical,
+}
+
+impl Default for SliderAxis {
+    fn default() -> Self {
+        SliderAxis::Horizontal
+    }
+}
+
+// Lives on the track, which receives the presses. The thumb is a child marked with SliderThumb.
+#[derive(Clone, Debug)]
+pub struct Slider {
+    pub value: f32,
+    pub min: f32,
+    pub max: f32,
+    // 0 for a continuous slider
+    pub step: f32,
+    pub axis: SliderAxis,
+    pub thumb_size: f32,
+}
+
+impl Default for Slider {
+    fn default() -> Self {
+        Self {
+            value: 0.,
+            min: 0.,
+            max: 1.,
+            step: 0.,
+            axis: Default::default(),
+            thumb_size: 20.,
+        }
+    }
+}
+
+impl Slider {
+    pub fn fraction(&self) -> f32 {
+        if self.max > self.min {
+            ((self.value - self.min) / (self.max - self.min)).clamp(0., 1.)
+        } else {
+            0.
+        }
+    }
+
+    pub fn value_at(&self, fraction: f32) -> f32 {
+        let mut value = self.min + fraction.clamp(0., 1.) * (self.max - self.min);
+        if self.step > 0. {
+            value = self.min + ((value - self.min) / self.step).round() * self.step;
+        }
+        // inverted sliders have min above max
+        value.clamp(self.min.min(self.max), self.min.max(self.max))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SliderThumb;
+
+pub fn spawn_slider(
+    commands: &mut Commands,
+    track: AUINode,
+    thumb: AUINode,
+    slider: Slider,
+) -> Entity {
+    let entity = commands
+        .spawn(track)
+        .with(slider)
+        .with(Interaction::default())
+        .current_entity()
+        .unwrap();
+    commands.with_children(|parent| {
+        parent.spawn(thumb).with(SliderThumb).with(PassThrough);
+    });
+    entity
+}
+
+// Places a node of the given size at `fraction` of the way along its parent, without ever
+// leaving it. Anchoring both edges to the fraction means the parent's length isn't needed.
+pub(crate) fn place_along(node: &mut ANode, axis: SliderAxis, fraction: f32, size: f32) {
+    let along = AxisConstraint::PaddingAndSize(-fraction * size, size);
+    let across = AxisConstraint::DoublePadding(0., 0.);
+    match axis {
+        SliderAxis::Horizontal => {
+            node.anchors = Anchors::new(fraction, fraction, 0., 1.);
+            node.constraint = Constraint::Independent {
+                x: along,
+                y: across,
+            };
+        }
+        SliderAxis::Vertical => {
+            node.anchors = Anchors::new(0., 1., fraction, fraction);
+            node.constraint = Constraint::Independent {
+                x: across,
+                y: along,
+            };
+        }
+    }
+}
+
+pub fn slider_system(
+    cursor: Res<UiCursor>,
+    direction: Res<LayoutDirection>,
+    mut events: ResMut<Events<ValueChanged<f32>>>,
+    nodes: Query<(Option<&ANode>, Option<&Parent>)>,
+    mut sliders: Query<(
+        Entity,
+        &mut Slider,
+        &Interaction,
+        &GlobalTransform,
+        &AuiRender,
+    )>,
+) {
+    let position = match cursor.position {
+        Some(position) => position,
+        None => return,
+    };
+    for (entity, mut slider, interaction, transform, render) in sliders.iter_mut() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let local = position - transform.translation.truncate() + render.size / 2.;
+        let (cursor, length) = match slider.axis {
+            SliderAxis::Horizontal => (local.x, render.size.x),
+            SliderAxis::Vertical => (local.y, render.size.y),
+        };
+        let travel = length - slider.thumb_size;
+        let mut fraction = if travel > 0. {
+            (cursor - slider.thumb_size / 2.) / travel
+        } else {
+            0.
+        };
+        // the thumb's placement is mirrored along with the rest of the layout
+        if slider.axis == SliderAxis::Horizontal
+            && resolve_direction(entity, *direction, &nodes) == LayoutDirection::RightToLeft
+        {
+            fraction = 1. - fraction;
+        }
+
+        let value = slider.value_at(fraction);
+        if value != slider.value {
+            slider.value = value;
+            events.send(ValueChanged { entity, value });
+        }
+    }
+}
+
+pub fn slider_thumb_system(
+    sliders: Query<(&Slider, &Children), Changed<Slider>>,
+    mut thumbs: Query<&mut ANode, With<SliderThumb>>,
+) {
+    for (slider, children) in sliders.iter() {
+        for child in children.iter() {
+            if let Ok(mut thumb) = thumbs.get_mut(*child) {
+                place_along(
+                    &mut thumb,
+                    slider.axis,
+                    slider.fraction(),
+                    slider.thumb_size,
+                );
+            }
+        }
+    }
+}
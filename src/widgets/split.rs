@@ -0,0 +1,167 @@
+use bevy_ecs::{Commands, Entity, Local, Query, Res, With};
+use bevy_math::Vec2;
+use bevy_transform::{
+    components::{Children, GlobalTransform, Parent},
+    hierarchy::BuildChildren,
+};
+use bevy_window::WindowId;
+
+use crate::{
+    ANode, AUINode, AuiRender, ChildConstraint, ChildLength, Direction, DisplayMode, Interaction,
+    SpreadConstraint, UiCursor,
+};
+
+// Dragging a splitter moves the boundary between the panes on either side of it in its parent's
+// spread, by changing their ChildConstraint weights. Only weighted panes can be resized.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Splitter;
+
+// Spreads the panes along `direction` with a splitter of `thickness` between each pair
+pub fn spawn_split(
+    commands: &mut Commands,
+    mut container: AUINode,
+    direction: Direction,
+    panes: Vec<AUINode>,
+    splitter: AUINode,
+    thickness: f32,
+) -> Entity {
+    container.node.children_spread = Some(SpreadConstraint {
+        direction,
+        ..Default::default()
+    });
+    let entity = commands.spawn(container).current_entity().unwrap();
+    commands.with_children(|parent| {
+        for (i, pane) in panes.into_iter().enumerate() {
+            if i != 0 {
+                let mut splitter = splitter.clone();
+                splitter.node.child_constraint = Some(ChildConstraint {
+                    length: ChildLength::Fixed(thickness),
+                    ..Default::default()
+                });
+                parent
+                    .spawn(splitter)
+                    .with(Splitter)
+                    .with(Interaction::default());
+            }
+            parent.spawn(pane);
+        }
+    });
+    entity
+}
+
+pub fn splitter_system(
+    cursor: Res<UiCursor>,
+    mut last_cursor: Local<Option<(Option<WindowId>, Vec2)>>,
+    splitters: Query<(Entity, &Interaction, &Parent), With<Splitter>>,
+    mut nodes: Query<(
+        &mut ANode,
+        Option<&Children>,
+        Option<&AuiRender>,
+        Option<&GlobalTransform>,
+        Option<&DisplayMode>,
+        Option<&Splitter>,
+    )>,
+) {
+    let position = match cursor.position {
+        Some(position) => position,
+        None => return,
+    };
+    // moving over to another window starts over from wherever the cursor is in it
+    let delta = match *last_cursor {
+        Some((window, last)) if window == cursor.window => position - last,
+        _ => Vec2::zero(),
+    };
+    *last_cursor = Some((cursor.window, position));
+    if delta == Vec2::zero() {
+        return;
+    }
+
+    for (splitter, interaction, parent) in splitters.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let horizontal = match nodes.get_component::<ANode>(parent.0).map(|container| {
+            container
+                .children_spread
+                .as_ref()
+                .map(|spread| &spread.direction)
+        }) {
+            Ok(Some(Direction::Left)) | Ok(Some(Direction::Right)) => true,
+            Ok(Some(Direction::Up)) | Ok(Some(Direction::Down)) => false,
+            _ => continue,
+        };
+        let siblings = match nodes.get_component::<Children>(parent.0) {
+            Ok(children) => children.iter().copied().collect::<Vec<_>>(),
+            Err(_) => continue,
+        };
+        let index = match siblings.iter().position(|sibling| *sibling == splitter) {
+            Some(index) => index,
+            None => continue,
+        };
+
+        let is_pane = |entity: &&Entity| {
+            let laid_out = nodes
+                .get_component::<ANode>(**entity)
+                .map_or(false, |node| !node.absolute);
+            let collapsed = nodes
+                .get_component::<DisplayMode>(**entity)
+                .map_or(false, |display| *display == DisplayMode::Collapsed);
+            laid_out && !collapsed && nodes.get_component::<Splitter>(**entity).is_err()
+        };
+        let before = siblings[..index].iter().rev().find(is_pane).copied();
+        let after = siblings[index + 1..].iter().find(is_pane).copied();
+        let (before, after) = match (before, after) {
+            (Some(before), Some(after)) => (before, after),
+            _ => continue,
+        };
+
+        let along = |v: Vec2| if horizontal { v.x } else { v.y };
+        let pane = |entity| {
+            let node = nodes.get_component::<ANode>(entity).unwrap();
+            let render = nodes.get_component::<AuiRender>(entity).unwrap();
+            let transform = nodes.get_component::<GlobalTransform>(entity).unwrap();
+            let constraint = node
+                .child_constraint
+                .clone()
+                .unwrap_or(ChildConstraint::DEFAULT);
+            (
+                constraint,
+                along(render.size),
+                along(transform.translation.truncate()),
+            )
+        };
+        let (before_constraint, before_size, before_position) = pane(before);
+        let (after_constraint, after_size, after_position) = pane(after);
+        if !matches!(before_constraint.length, ChildLength::Weighted)
+            || !matches!(after_constraint.length, ChildLength::Weighted)
+        {
+            continue;
+        }
+
+        // reversed spreads (and mirrored layouts) put later children first on screen
+        let sign = if after_position >= before_position {
+            1.
+        } else {
+            -1.
+        };
+        let total = before_size + after_size;
+        let moved = (sign * along(delta))
+            .max(before_constraint.min_size - before_size)
+            .max(after_size - after_constraint.max_size)
+            .min(before_constraint.max_size - before_size)
+            .min(after_size - after_constraint.min_size);
+        if moved == 0. || total <= 0. {
+            continue;
+        }
+
+        let weight = before_constraint.weight + after_constraint.weight;
+        let before_weight = weight * (before_size + moved) / total;
+        let mut set_weight = |entity, mut constraint: ChildConstraint, weight| {
+            constraint.weight = weight;
+            let mut node = nodes.get_component_mut::<ANode>(entity).unwrap();
+            node.child_constraint = Some(constraint);
+        };
+        set_weight(before, before_constraint, before_weight);
+        set_weight(after, after_constraint, weight - before_weight);
+    }
+}
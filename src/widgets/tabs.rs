@@ -0,0 +1,244 @@
+use std::sync::Arc;
+
+use bevy_app::{EventReader, Events};
+use bevy_core::Time;
+use bevy_ecs::{Changed, Commands, Entity, Local, Query, Res, ResMut};
+use bevy_transform::{
+    components::Children,
+    hierarchy::{BuildChildren, DespawnRecursiveExt},
+};
+
+use crate::{
+    ANode, AUINode, Anchors, ChildConstraint, ChildLength, Clicked, Direction, DisplayMode,
+    Interaction, SpreadConstraint, ValueChanged,
+};
+
+// Exactly one TabPage of these tabs is shown at a time, the others are collapsed out of layout.
+// Without `keep_state`, pages lose their children when they're switched away from, and get them
+// back from TabPage::build when they're switched to again. `build` is handed the page and its
+// index, and can capture what the pages show.
+#[derive(Clone, Debug)]
+pub struct Tabs {
+    pub active: usize,
+    pub keep_state: bool,
+    // Length of the slide when switching pages, in seconds
+    pub animation: Option<f32>,
+    shown: Option<usize>,
+}
+
+impl Tabs {
+    pub fn new(active: usize) -> Self {
+        Self {
+            active,
+            keep_state: true,
+            animation: None,
+            shown: None,
+        }
+    }
+}
+
+impl Default for Tabs {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+// Switches `tabs` to `index` when clicked
+#[derive(Clone, Copy, Debug)]
+pub struct TabButton {
+    pub tabs: Entity,
+    pub index: usize,
+}
+
+pub type TabBuild = Arc<dyn Fn(&mut Commands, Entity, usize) + Send + Sync>;
+
+#[derive(Clone)]
+pub struct TabPage {
+    pub tabs: Entity,
+    pub index: usize,
+    pub build: Option<TabBuild>,
+}
+
+// Slides a page in from one side by offsetting its horizontal anchors
+#[derive(Clone, Debug)]
+pub struct TabSlide {
+    pub elapsed: f32,
+    pub duration: f32,
+    // In multiples of the parent's width, positive for coming in from the right
+    pub from: f32,
+    pub anchors: Anchors,
+}
+
+// Spawns the tabs as a header of buttons spread over `header_height` above a content node
+// holding the pages. Returns the entity with the Tabs component.
+pub fn spawn_tabs(
+    commands: &mut Commands,
+    mut container: AUINode,
+    mut header: AUINode,
+    content: AUINode,
+    header_height: f32,
+    pages: Vec<(AUINode, AUINode, Option<TabBuild>)>,
+    tabs: Tabs,
+) -> Entity {
+    container.node.children_spread = Some(SpreadConstraint {
+        direction: Direction::Down,
+        ..Default::default()
+    });
+    header.node.child_constraint = Some(ChildConstraint {
+        length: ChildLength::Fixed(header_height),
+        ..Default::default()
+    });
+    header.node.children_spread = Some(SpreadConstraint {
+        direction: Direction::Right,
+        ..Default::default()
+    });
+
+    let entity = commands
+        .spawn(container)
+        .with(tabs)
+        .current_entity()
+        .unwrap();
+    let header = commands.spawn(header).current_entity().unwrap();
+    let content = commands.spawn(content).current_entity().unwrap();
+    commands.push_children(entity, &[header, content]);
+    for (index, (button, mut page, build)) in pages.into_iter().enumerate() {
+        page.node.anchors = Anchors::FULL;
+        let button = commands
+            .spawn(button)
+            .with(TabButton {
+                tabs: entity,
+                index,
+            })
+            .with(Interaction::default())
+            .current_entity()
+            .unwrap();
+        let page = commands
+            .spawn(page)
+            .with(TabPage {
+                tabs: entity,
+                index,
+                build,
+            })
+            .current_entity()
+            .unwrap();
+        commands.push_children(header, &[button]);
+        commands.push_children(content, &[page]);
+    }
+    entity
+}
+
+pub fn tab_button_system(
+    mut reader: Local<EventReader<Clicked>>,
+    clicks: Res<Events<Clicked>>,
+    mut events: ResMut<Events<ValueChanged<usize>>>,
+    buttons: Query<&TabButton>,
+    mut tabs: Query<&mut Tabs>,
+) {
+    for click in reader.iter(&clicks) {
+        let button = match buttons.get(click.entity) {
+            Ok(button) => button,
+            Err(_) => continue,
+        };
+        if let Ok(mut tabs) = tabs.get_mut(button.tabs) {
+            if tabs.active != button.index {
+                tabs.active = button.index;
+                events.send(ValueChanged {
+                    entity: button.tabs,
+                    value: button.index,
+                });
+            }
+        }
+    }
+}
+
+pub fn tab_page_system(
+    commands: &mut Commands,
+    mut tabs: Query<(Entity, &mut Tabs), Changed<Tabs>>,
+    mut pages: Query<(
+        Entity,
+        &TabPage,
+        &mut DisplayMode,
+        &mut ANode,
+        Option<&TabSlide>,
+        Option<&Children>,
+    )>,
+) {
+    for (tabs_entity, mut tabs) in tabs.iter_mut() {
+        if tabs.shown == Some(tabs.active) {
+            continue;
+        }
+        let previous = tabs.shown;
+        tabs.shown = Some(tabs.active);
+
+        for (entity, page, mut display, mut node, slide, children) in pages.iter_mut() {
+            if page.tabs != tabs_entity {
+                continue;
+            }
+            if page.index == tabs.active {
+                *display = DisplayMode::Shown;
+                if children.map_or(true, |children| children.is_empty()) {
+                    if let Some(build) = &page.build {
+                        build(commands, entity, page.index);
+                    }
+                }
+                if let (Some(duration), Some(previous)) = (tabs.animation, previous) {
+                    // a page still sliding has its resting anchors in the slide, not the node
+                    let anchors =
+                        slide.map_or_else(|| node.anchors.clone(), |slide| slide.anchors.clone());
+                    commands.insert_one(
+                        entity,
+                        TabSlide {
+                            elapsed: 0.,
+                            duration,
+                            from: if page.index > previous { 1. } else { -1. },
+                            anchors,
+                        },
+                    );
+                }
+            } else {
+                *display = DisplayMode::Collapsed;
+                if let Some(slide) = slide {
+                    node.anchors = slide.anchors.clone();
+                    commands.remove_one::<TabSlide>(entity);
+                }
+                if !tabs.keep_state {
+                    for child in children.iter().flat_map(|children| children.iter()) {
+                        commands.despawn_recursive(*child);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn tab_slide_system(
+    commands: &mut Commands,
+    time: Res<Time>,
+    mut slides: Query<(Entity, &mut TabSlide, &mut ANode, &DisplayMode)>,
+) {
+    for (entity, mut slide, mut node, display) in slides.iter_mut() {
+        // pages switched away from mid slide are put back to rest
+        if *display == DisplayMode::Collapsed {
+            node.anchors = slide.anchors.clone();
+            commands.remove_one::<TabSlide>(entity);
+            continue;
+        }
+        slide.elapsed += time.delta_seconds();
+        let t = if slide.duration > 0. {
+            (slide.elapsed / slide.duration).min(1.)
+        } else {
+            1.
+        };
+        // ease out, so the page slows down as it settles
+        let offset = slide.from * (1. - t) * (1. - t);
+        node.anchors = Anchors::new(
+            slide.anchors.left + offset,
+            slide.anchors.right + offset,
+            slide.anchors.bottom,
+            slide.anchors.top,
+        );
+        if t >= 1. {
+            commands.remove_one::<TabSlide>(entity);
+        }
+    }
+}
@@ -0,0 +1,428 @@
+use bevy_app::{EventReader, Events};
+use bevy_ecs::{Commands, Entity, Flags, Local, Query, Res, ResMut};
+use bevy_input::{keyboard::KeyCode, Input};
+use bevy_transform::{components::Children, hierarchy::BuildChildren};
+use bevy_window::ReceivedCharacter;
+
+use crate::{
+    ANode, AUINode, Anchors, AuiRender, AxisConstraint, Constraint, DisplayMode, Focusable,
+    Interaction, PassThrough, UiFocus, ValueChanged,
+};
+
+// Text is measured with fixed per character metrics, as the crate doesn't shape text itself.
+// `display_text` is what a text renderer should draw, shifted left by `scroll`, with
+// `composition` drawn at the caret while an input method is composing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextInput {
+    pub value: String,
+    pub placeholder: String,
+    // In characters
+    pub max_length: Option<usize>,
+    pub multiline: bool,
+    // Character indices into value
+    pub caret: usize,
+    pub selection_anchor: Option<usize>,
+    // Horizontal scroll in pixels, which keeps the caret inside the node
+    pub scroll: f32,
+    pub char_width: f32,
+    pub line_height: f32,
+    // Text an input method is still composing, it's only added to value once committed
+    pub composition: String,
+}
+
+impl Default for TextInput {
+    fn default() -> Self {
+        Self {
+            value: String::new(),
+            placeholder: String::new(),
+            max_length: None,
+            multiline: false,
+            caret: 0,
+            selection_anchor: None,
+            scroll: 0.,
+            char_width: 10.,
+            line_height: 20.,
+            composition: String::new(),
+        }
+    }
+}
+
+impl TextInput {
+    pub fn display_text(&self) -> &str {
+        if self.value.is_empty() {
+            &self.placeholder
+        } else {
+            &self.value
+        }
+    }
+
+    pub fn selection(&self) -> Option<(usize, usize)> {
+        let anchor = self.selection_anchor?;
+        if anchor == self.caret {
+            None
+        } else {
+            Some((anchor.min(self.caret), anchor.max(self.caret)))
+        }
+    }
+
+    pub fn selected_text(&self) -> &str {
+        match self.selection() {
+            Some((start, end)) => &self.value[self.byte_index(start)..self.byte_index(end)],
+            None => "",
+        }
+    }
+
+    fn char_len(&self) -> usize {
+        self.value.chars().count()
+    }
+
+    fn byte_index(&self, index: usize) -> usize {
+        self.value
+            .char_indices()
+            .nth(index)
+            .map_or(self.value.len(), |(byte, _)| byte)
+    }
+
+    // Zero based line and column of a character index
+    pub fn line_and_column(&self, index: usize) -> (usize, usize) {
+        let before = &self.value[..self.byte_index(index)];
+        let line = before.matches('\n').count();
+        let column = before.rsplit('\n').next().unwrap_or("").chars().count();
+        (line, column)
+    }
+
+    fn index_at(&self, line: usize, column: usize) -> usize {
+        let mut index = 0;
+        for (i, text) in self.value.split('\n').enumerate() {
+            let len = text.chars().count();
+            if i == line {
+                return index + column.min(len);
+            }
+            index += len + 1;
+        }
+        self.char_len()
+    }
+
+    fn move_caret(&mut self, to: usize, select: bool) {
+        if select {
+            if self.selection_anchor.is_none() {
+                self.selection_anchor = Some(self.caret);
+            }
+        } else {
+            self.selection_anchor = None;
+        }
+        self.caret = to;
+    }
+
+    fn delete_selection(&mut self) -> bool {
+        match self.selection() {
+            Some((start, end)) => {
+                let range = self.byte_index(start)..self.byte_index(end);
+                self.value.replace_range(range, "");
+                self.caret = start;
+                self.selection_anchor = None;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // Replaces the selection with text, cutting it short to respect multiline and max_length
+    pub fn insert(&mut self, text: &str) -> bool {
+        let deleted = self.delete_selection();
+        let text = if self.multiline {
+            text
+        } else {
+            text.split('\n').next().unwrap_or("")
+        };
+        let room = self
+            .max_length
+            .map_or(usize::MAX, |max| max.saturating_sub(self.char_len()));
+        let text = match text.char_indices().nth(room) {
+            Some((byte, _)) => &text[..byte],
+            None => text,
+        };
+        if text.is_empty() {
+            return deleted;
+        }
+        let at = self.byte_index(self.caret);
+        self.value.insert_str(at, text);
+        self.caret += text.chars().count();
+        true
+    }
+}
+
+// Copy and cut write here and paste reads from here. With the `clipboard` feature the text is
+// also shared with other applications through the system clipboard, which paste prefers.
+#[derive(Clone, Debug, Default)]
+pub struct UiClipboard {
+    pub contents: String,
+}
+
+impl UiClipboard {
+    pub fn set(&mut self, text: &str) {
+        #[cfg(feature = "clipboard")]
+        let _ = arboard::Clipboard::new().and_then(|mut system| system.set_text(text.to_string()));
+        self.contents = text.to_string();
+    }
+
+    pub fn get(&self) -> String {
+        #[cfg(feature = "clipboard")]
+        let system = arboard::Clipboard::new()
+            .and_then(|mut system| system.get_text())
+            .ok();
+        #[cfg(not(feature = "clipboard"))]
+        let system: Option<String> = None;
+        system.unwrap_or_else(|| self.contents.clone())
+    }
+}
+
+// The preedit text of an input method, for the focused TextInput. bevy_window doesn't report
+// input method composition, so these have to be sent by the app from its windowing backend.
+// An empty text ends the composition, the committed text then arrives as ReceivedCharacter.
+#[derive(Clone, Debug, Default)]
+pub struct TextComposition {
+    pub text: String,
+}
+
+// Sent when enter is pressed in a single line TextInput
+#[derive(Clone, Debug)]
+pub struct TextSubmitted {
+    pub entity: Entity,
+    pub value: String,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TextCaret;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TextSelection;
+
+pub fn spawn_text_input(
+    commands: &mut Commands,
+    frame: AUINode,
+    caret: AUINode,
+    selection: AUINode,
+    input: TextInput,
+) -> Entity {
+    let entity = commands
+        .spawn(frame)
+        .with(input)
+        .with(Interaction::default())
+        .with(Focusable)
+        .current_entity()
+        .unwrap();
+    commands.with_children(|parent| {
+        parent
+            .spawn(selection)
+            .with(TextSelection)
+            .with(PassThrough);
+        parent.spawn(caret).with(TextCaret).with(PassThrough);
+    });
+    entity
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn text_input_system(
+    mut reader: Local<EventReader<ReceivedCharacter>>,
+    characters: Res<Events<ReceivedCharacter>>,
+    mut composition_reader: Local<EventReader<TextComposition>>,
+    compositions: Res<Events<TextComposition>>,
+    keys: Res<Input<KeyCode>>,
+    focus: Res<UiFocus>,
+    mut clipboard: ResMut<UiClipboard>,
+    mut changed: ResMut<Events<ValueChanged<String>>>,
+    mut submitted: ResMut<Events<TextSubmitted>>,
+    mut inputs: Query<(&mut TextInput, &AuiRender)>,
+) {
+    let typed = reader
+        .iter(&characters)
+        .map(|event| event.char)
+        .filter(|c| !c.is_control())
+        .collect::<String>();
+    let composition = composition_reader
+        .iter(&compositions)
+        .last()
+        .map(|event| event.text.clone());
+
+    let entity = match focus.entity {
+        Some(entity) => entity,
+        None => return,
+    };
+    let (mut target, render) = match inputs.get_mut(entity) {
+        Ok(input) => input,
+        Err(_) => return,
+    };
+
+    let mut input = target.clone();
+    let shift = keys.pressed(KeyCode::LShift) || keys.pressed(KeyCode::RShift);
+    let ctrl = keys.pressed(KeyCode::LControl) || keys.pressed(KeyCode::RControl);
+    let mut edited = false;
+
+    if ctrl {
+        if keys.just_pressed(KeyCode::A) {
+            input.selection_anchor = Some(0);
+            input.caret = input.char_len();
+        }
+        if keys.just_pressed(KeyCode::C) || keys.just_pressed(KeyCode::X) {
+            if input.selection().is_some() {
+                clipboard.set(input.selected_text());
+            }
+            if keys.just_pressed(KeyCode::X) {
+                edited |= input.delete_selection();
+            }
+        }
+        if keys.just_pressed(KeyCode::V) {
+            edited |= input.insert(&clipboard.get());
+        }
+    } else if !typed.is_empty() {
+        edited |= input.insert(&typed);
+        input.composition.clear();
+    }
+    if let Some(composition) = composition {
+        input.composition = composition;
+    }
+
+    if keys.just_pressed(KeyCode::Back) {
+        if input.selection().is_none() && input.caret > 0 {
+            input.selection_anchor = Some(input.caret - 1);
+        }
+        edited |= input.delete_selection();
+    }
+    if keys.just_pressed(KeyCode::Delete) {
+        if input.selection().is_none() && input.caret < input.char_len() {
+            input.selection_anchor = Some(input.caret + 1);
+        }
+        edited |= input.delete_selection();
+    }
+    if keys.just_pressed(KeyCode::Return) {
+        if input.multiline {
+            edited |= input.insert("\n");
+        } else {
+            submitted.send(TextSubmitted {
+                entity,
+                value: input.value.clone(),
+            });
+        }
+    }
+
+    let (line, column) = input.line_and_column(input.caret);
+    if keys.just_pressed(KeyCode::Left) {
+        input.move_caret(input.caret.saturating_sub(1), shift);
+    }
+    if keys.just_pressed(KeyCode::Right) {
+        input.move_caret((input.caret + 1).min(input.char_len()), shift);
+    }
+    if keys.just_pressed(KeyCode::Home) {
+        input.move_caret(input.index_at(line, 0), shift);
+    }
+    if keys.just_pressed(KeyCode::End) {
+        input.move_caret(input.index_at(line, usize::MAX), shift);
+    }
+    if keys.just_pressed(KeyCode::Up) && line > 0 {
+        input.move_caret(input.index_at(line - 1, column), shift);
+    }
+    if keys.just_pressed(KeyCode::Down) {
+        input.move_caret(input.index_at(line + 1, column), shift);
+    }
+
+    let caret_x = input.line_and_column(input.caret).1 as f32 * input.char_width;
+    let visible = (render.size.x - input.char_width).max(0.);
+    if caret_x - input.scroll > visible {
+        input.scroll = caret_x - visible;
+    }
+    if caret_x < input.scroll {
+        input.scroll = caret_x;
+    }
+
+    if edited {
+        changed.send(ValueChanged {
+            entity,
+            value: input.value.clone(),
+        });
+    }
+    if *target != input {
+        *target = input;
+    }
+}
+
+// Moves the caret and selection children to match the input, hiding them without focus
+pub fn text_input_visual_system(
+    focus: Res<UiFocus>,
+    mut last_focus: Local<Option<Entity>>,
+    inputs: Query<(Entity, &TextInput, Flags<TextInput>, &Children)>,
+    mut markers: Query<(
+        &mut ANode,
+        &mut DisplayMode,
+        Option<&TextCaret>,
+        Option<&TextSelection>,
+    )>,
+) {
+    let focus_changed = *last_focus != focus.entity;
+    *last_focus = focus.entity;
+
+    for (entity, input, flags, children) in inputs.iter() {
+        if !flags.changed() && !focus_changed {
+            continue;
+        }
+        let focused = focus.entity == Some(entity);
+        let (caret_line, caret_column) = input.line_and_column(input.caret);
+        let selection = input.selection();
+
+        for child in children.iter() {
+            let (mut node, mut display, caret, selection_marker) = match markers.get_mut(*child) {
+                Ok(marker) => marker,
+                Err(_) => continue,
+            };
+            if caret.is_some() {
+                *display = if focused {
+                    DisplayMode::Shown
+                } else {
+                    DisplayMode::Hidden
+                };
+                node.anchors = Anchors::TOP_LEFT;
+                node.constraint = Constraint::Independent {
+                    x: AxisConstraint::PaddingAndSize(
+                        caret_column as f32 * input.char_width - input.scroll,
+                        1.5,
+                    ),
+                    y: AxisConstraint::InversePaddingAndSize(
+                        caret_line as f32 * input.line_height,
+                        input.line_height,
+                    ),
+                };
+            } else if selection_marker.is_some() {
+                let (start, end) = match selection.filter(|_| focused) {
+                    Some(selection) => selection,
+                    None => {
+                        *display = DisplayMode::Hidden;
+                        continue;
+                    }
+                };
+                *display = DisplayMode::Shown;
+                let (start_line, start_column) = input.line_and_column(start);
+                let (end_line, end_column) = input.line_and_column(end);
+                let y = AxisConstraint::InversePaddingAndSize(
+                    start_line as f32 * input.line_height,
+                    (end_line - start_line + 1) as f32 * input.line_height,
+                );
+                if start_line == end_line {
+                    node.anchors = Anchors::TOP_LEFT;
+                    node.constraint = Constraint::Independent {
+                        x: AxisConstraint::PaddingAndSize(
+                            start_column as f32 * input.char_width - input.scroll,
+                            (end_column - start_column) as f32 * input.char_width,
+                        ),
+                        y,
+                    };
+                } else {
+                    node.anchors = Anchors::TOP_FULL;
+                    node.constraint = Constraint::Independent {
+                        x: AxisConstraint::DoublePadding(0., 0.),
+                        y,
+                    };
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use bevy_core::Time;
+use bevy_ecs::{Commands, Entity, Local, Query, Res, With, Without};
+use bevy_math::Vec2;
+use bevy_transform::{
+    components::{GlobalTransform, Parent},
+    hierarchy::{BuildChildren, DespawnRecursiveExt},
+};
+use bevy_window::Windows;
+
+use crate::{
+    ANode, AUINode, Anchors, AuiRender, AxisConstraint, Constraint, Interaction, LayoutDirection,
+    UiCursor,
+};
+
+// Gap between the tooltip and the cursor or node it's placed next to
+pub const TOOLTIP_OFFSET: f32 = 12.;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TooltipPlacement {
+    Cursor,
+    Above,
+    Below,
+}
+
+impl Default for TooltipPlacement {
+    fn default() -> Self {
+        TooltipPlacement::Cursor
+    }
+}
+
+// Shows an overlay of `size` after the node has been hovered for `delay` seconds. The overlay is
+// spawned from `frame` under the node's root, and `build` is handed the overlay and the hovered
+// node to fill it with any nodes it likes. `build` can capture what it needs to describe the node.
+// The node needs an Interaction to be hovered.
+#[derive(Clone)]
+pub struct Tooltip {
+    pub delay: f32,
+    pub size: Vec2,
+    pub placement: TooltipPlacement,
+    pub frame: AUINode,
+    pub build: Arc<dyn Fn(&mut Commands, Entity, Entity) + Send + Sync>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TooltipOverlay;
+
+#[derive(Default)]
+pub struct TooltipState {
+    hovered: Option<Entity>,
+    elapsed: f32,
+    shown: Option<Entity>,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn tooltip_system(
+    commands: &mut Commands,
+    mut state: Local<TooltipState>,
+    time: Res<Time>,
+    cursor: Res<UiCursor>,
+    windows: Res<Windows>,
+    direction: Res<LayoutDirection>,
+    tooltips: Query<(Entity, &Tooltip, &Interaction)>,
+    rects: Query<(&GlobalTransform, &AuiRender)>,
+    parents: Query<&Parent>,
+    roots: Query<&ANode, Without<TooltipOverlay>>,
+    mut overlays: Query<&mut ANode, With<TooltipOverlay>>,
+) {
+    let hovered = tooltips
+        .iter()
+        .find(|(_, _, interaction)| **interaction == Interaction::Hovered)
+        .map(|(entity, ..)| entity);
+    if hovered != state.hovered {
+        if let Some(shown) = state.shown.take() {
+            commands.despawn_recursive(shown);
+        }
+        state.hovered = hovered;
+        state.elapsed = 0.;
+    }
+    let (entity, position) = match (hovered, cursor.position) {
+        (Some(entity), Some(position)) => (entity, position),
+        _ => return,
+    };
+    state.elapsed += time.delta_seconds();
+    let (_, tooltip, _) = tooltips.get(entity).unwrap();
+    if state.elapsed < tooltip.delay {
+        return;
+    }
+
+    let mut root = entity;
+    while let Ok(parent) = parents.get(root) {
+        root = parent.0;
+    }
+    let (root_transform, root_render) = match rects.get(root) {
+        Ok(rect) => rect,
+        Err(_) => return,
+    };
+    let (node_transform, node_render) = rects.get(entity).unwrap();
+
+    // bottom left corner of the tooltip in UI space, kept inside the window
+    let node_center = node_transform.translation.truncate();
+    let size = tooltip.size;
+    let corner = match tooltip.placement {
+        TooltipPlacement::Cursor => position + Vec2::new(TOOLTIP_OFFSET, -TOOLTIP_OFFSET - size.y),
+        TooltipPlacement::Above => {
+            node_center + Vec2::new(-size.x / 2., node_render.size.y / 2. + TOOLTIP_OFFSET)
+        }
+        TooltipPlacement::Below => {
+            node_center
+                - Vec2::new(
+                    size.x / 2.,
+                    node_render.size.y / 2. + TOOLTIP_OFFSET + size.y,
+                )
+        }
+    };
+    let window = cursor
+        .window
+        .and_then(|window| windows.get(window))
+        .or_else(|| windows.get_primary());
+    let half_window = window.map_or(Vec2::zero(), |window| {
+        Vec2::new(window.width(), window.height()) / 2.
+    });
+    let max = (half_window - size).max(-half_window);
+    let corner = corner.max(-half_window).min(max);
+
+    // tooltips are placed from the bottom left corner of the root, which right to left layouts
+    // mirror, so there they're placed from the bottom right one for it to end up the same
+    let root_corner = root_transform.translation.truncate() - root_render.size / 2.;
+    let offset = corner - root_corner;
+    let root_direction = roots
+        .get(root)
+        .ok()
+        .and_then(|node| node.layout_direction)
+        .unwrap_or(*direction);
+    let place = |node: &mut ANode| {
+        let rtl = node.layout_direction.unwrap_or(root_direction) == LayoutDirection::RightToLeft;
+        let constraint = Constraint::Independent {
+            x: AxisConstraint::PaddingAndSize(offset.x, size.x),
+            y: AxisConstraint::PaddingAndSize(offset.y, size.y),
+        };
+        if rtl {
+            node.anchors = Anchors::BOTTOM_LEFT.mirrored_x();
+            node.constraint = constraint.mirrored();
+        } else {
+            node.anchors = Anchors::BOTTOM_LEFT;
+            node.constraint = constraint;
+        }
+    };
+
+    match state.shown {
+        Some(shown) => {
+            if let Ok(mut node) = overlays.get_mut(shown) {
+                if let Constraint::Independent {
+                    x,
+                    y: AxisConstraint::PaddingAndSize(y, _),
+                } = node.constraint
+                {
+                    let x = match x {
+                        AxisConstraint::PaddingAndSize(x, _)
+                        | AxisConstraint::InversePaddingAndSize(x, _) => Some(x),
+                        _ => None,
+                    };
+                    if x.map(|x| Vec2::new(x, y)) == Some(offset) {
+                        return;
+                    }
+                }
+                place(&mut node);
+            }
+        }
+        None => {
+            let mut frame = tooltip.frame.clone();
+            frame.node.absolute = true;
+            frame.node.overlay = true;
+            place(&mut frame.node);
+            let shown = commands
+                .spawn(frame)
+                .with(TooltipOverlay)
+                .current_entity()
+                .unwrap();
+            commands.push_children(root, &[shown]);
+            (tooltip.build)(commands, shown, entity);
+            state.shown = Some(shown);
+        }
+    }
+}
@@ -0,0 +1,219 @@
+use std::{collections::HashMap, sync::Arc};
+
+use bevy_app::{EventReader, Events};
+use bevy_ecs::{Commands, Entity, Local, Query, Res, With};
+use bevy_input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy_math::Vec4;
+use bevy_transform::{
+    components::{Children, GlobalTransform, Parent},
+    hierarchy::BuildChildren,
+};
+
+use crate::{
+    interaction::contains, ANode, AUINode, Anchors, AuiRender, AxisConstraint, Constraint,
+    DisplayMode, UiCursor, UiTargets,
+};
+
+// A scrolling list that only keeps entities for the rows inside its viewport. Rows are spawned
+// from `row_template` as absolute children and handed to `bind_row` along with the data index
+// they show whenever that changes, so `bind_row` has to overwrite anything an earlier bind left.
+// `bind_row` can capture the list's data source, an Arc<RwLock<Vec<_>>> for instance.
+// Whatever is inside the list is clipped to its rect, so rows partly scrolled out of the viewport
+// are cut off at its edges.
+#[derive(Clone)]
+pub struct VirtualList {
+    pub len: usize,
+    // No rows are shown unless this is positive
+    pub row_height: f32,
+    pub scroll: f32,
+    pub row_template: AUINode,
+    pub bind_row: Arc<dyn Fn(&mut Commands, Entity, usize) + Send + Sync>,
+    // Set to bind every visible row again, for when the data changed but `len` didn't
+    pub rebind: bool,
+    rows: Vec<(Entity, Option<usize>)>,
+    last: Option<(f32, f32, usize)>,
+}
+
+impl VirtualList {
+    pub fn new(
+        len: usize,
+        row_height: f32,
+        row_template: AUINode,
+        bind_row: impl Fn(&mut Commands, Entity, usize) + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            len,
+            row_height,
+            scroll: 0.,
+            row_template,
+            bind_row: Arc::new(bind_row),
+            rebind: false,
+            rows: vec![],
+            last: None,
+        }
+    }
+
+    pub fn max_scroll(&self, viewport_height: f32) -> f32 {
+        (self.len as f32 * self.row_height - viewport_height).max(0.)
+    }
+
+    fn place(&self, node: &mut ANode, index: usize) {
+        node.absolute = true;
+        node.anchors = Anchors::TOP_FULL;
+        node.constraint = Constraint::Independent {
+            x: AxisConstraint::DoublePadding(0., 0.),
+            y: AxisConstraint::InversePaddingAndSize(
+                index as f32 * self.row_height - self.scroll,
+                self.row_height,
+            ),
+        };
+    }
+}
+
+pub fn virtual_list_system(
+    commands: &mut Commands,
+    mut reader: Local<EventReader<MouseWheel>>,
+    wheel: Res<Events<MouseWheel>>,
+    cursor: Res<UiCursor>,
+    targets: Res<UiTargets>,
+    mut lists: Query<(Entity, &mut VirtualList, &GlobalTransform, &AuiRender)>,
+    mut rows: Query<(&mut ANode, &mut DisplayMode)>,
+) {
+    let (lines, pixels) = reader
+        .iter(&wheel)
+        .fold((0., 0.), |(lines, pixels), event| match event.unit {
+            MouseScrollUnit::Line => (lines + event.y, pixels),
+            MouseScrollUnit::Pixel => (lines, pixels + event.y),
+        });
+
+    for (entity, mut list, transform, render) in lists.iter_mut() {
+        let height = render.size.y;
+        let hovered = match (cursor.position, cursor.window) {
+            (Some(position), Some(window)) => {
+                targets.is_in_window(entity, window) && contains(transform, render, position)
+            }
+            _ => false,
+        };
+        let max_scroll = list.max_scroll(height);
+        if hovered && (lines != 0. || pixels != 0.) {
+            let delta = lines * list.row_height + pixels;
+            list.scroll = (list.scroll - delta).clamp(0., max_scroll);
+        } else if list.scroll > max_scroll {
+            list.scroll = max_scroll;
+        }
+
+        let state = (list.scroll, height, list.len);
+        if list.last == Some(state) && !list.rebind {
+            continue;
+        }
+        let rebind = list.rebind;
+        list.last = Some(state);
+        list.rebind = false;
+
+        let visible = if list.row_height > 0. {
+            let first = (list.scroll / list.row_height).floor().max(0.) as usize;
+            let end =
+                (((list.scroll + height) / list.row_height).ceil().max(0.) as usize).min(list.len);
+            first.min(end)..end
+        } else {
+            0..0
+        };
+
+        // rows keep their index while it stays visible, the rest are recycled for new indices
+        let mut rows_list = std::mem::take(&mut list.rows);
+        let mut missing = visible
+            .clone()
+            .filter(|index| !rows_list.iter().any(|(_, bound)| *bound == Some(*index)))
+            .collect::<Vec<_>>()
+            .into_iter();
+        for (row, bound) in rows_list.iter_mut() {
+            let keep = bound.map_or(false, |index| visible.contains(&index));
+            if !keep {
+                *bound = missing.next();
+                if let Some(index) = *bound {
+                    (list.bind_row)(commands, *row, index);
+                }
+            } else if rebind {
+                (list.bind_row)(commands, *row, bound.unwrap());
+            }
+            if let Ok((mut node, mut display)) = rows.get_mut(*row) {
+                match *bound {
+                    Some(index) => {
+                        list.place(&mut node, index);
+                        *display = DisplayMode::Shown;
+                    }
+                    None => *display = DisplayMode::Collapsed,
+                }
+            }
+        }
+        for index in missing {
+            let mut row = list.row_template.clone();
+            list.place(&mut row.node, index);
+            let row = commands.spawn(row).current_entity().unwrap();
+            commands.push_children(entity, &[row]);
+            (list.bind_row)(commands, row, index);
+            rows_list.push((row, Some(index)));
+        }
+        list.rows = rows_list;
+    }
+}
+
+// Runs after layout, with the lists' rects from the last frame, which only lag behind while a
+// list itself moves
+pub fn virtual_list_clip_system(
+    lists: Query<(Entity, &GlobalTransform), With<VirtualList>>,
+    parents: Query<&Parent>,
+    children: Query<&Children>,
+    mut renders: Query<&mut AuiRender>,
+) {
+    fn clip(
+        entity: Entity,
+        rect: Vec4,
+        rects: &HashMap<Entity, Vec4>,
+        children: &Query<&Children>,
+        renders: &mut Query<&mut AuiRender>,
+    ) {
+        for child in children
+            .get(entity)
+            .into_iter()
+            .flat_map(|children| children.iter())
+        {
+            if let Ok(mut render_data) = renders.get_mut(*child) {
+                if render_data.clip != rect {
+                    render_data.clip = rect;
+                }
+            }
+            // lists inside this one clip their own contents, to both rects
+            if !rects.contains_key(child) {
+                clip(*child, rect, rects, children, renders);
+            }
+        }
+    }
+
+    let rects = lists
+        .iter()
+        .filter_map(|(entity, transform)| {
+            let render_data = renders.get_component::<AuiRender>(entity).ok()?;
+            let center = transform.translation.truncate();
+            let half = render_data.size / 2.;
+            let (min, max) = (center - half, center + half);
+            Some((entity, Vec4::new(min.x, min.y, max.x, max.y)))
+        })
+        .collect::<HashMap<_, _>>();
+    for (entity, rect) in rects.iter() {
+        let mut rect = *rect;
+        let mut ancestor = *entity;
+        while let Ok(parent) = parents.get(ancestor) {
+            ancestor = parent.0;
+            if let Some(outer) = rects.get(&ancestor) {
+                rect = Vec4::new(
+                    rect.x.max(outer.x),
+                    rect.y.max(outer.y),
+                    rect.z.min(outer.z),
+                    rect.w.min(outer.w),
+                );
+            }
+        }
+        clip(*entity, rect, &rects, &children, &mut renders);
+    }
+}